@@ -11,23 +11,37 @@
  * SPDX-License-Identifier: EPL-2.0
  */
 
+use crate::lockfile::LockfileSource;
+use crate::metadata::MetadataSource;
+use crate::pattern::Selector;
+use crate::policy::{CategoryRule, ScoreOverride, ScoreRequirement, ScoreType};
+use crate::report::{Options, OutputFormat};
 use anyhow::anyhow;
 use clap::{ArgAction, ValueEnum};
+use semver::Version;
 use spdx::LicenseId;
 use std::path::PathBuf;
 use std::str::FromStr;
 
-#[derive(Debug, Clone, Copy, ValueEnum)]
-pub enum OutputFormat {
-    Text,
-    Csv,
-    Markdown,
+/// A report, written to a file.
+#[derive(Debug, Clone)]
+pub struct OutputFile {
+    pub format: OutputFormat,
+    pub path: PathBuf,
 }
 
-#[derive(Debug, Clone, Copy, ValueEnum)]
-pub enum ScoreType {
-    Effective,
-    Licensed,
+impl FromStr for OutputFile {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            Some((format, path)) => Ok(OutputFile {
+                format: OutputFormat::from_str(format.trim(), true).map_err(|e| anyhow!(e))?,
+                path: path.into(),
+            }),
+            None => Err(anyhow!("Missing path, expected <format>=<path>: {}", s)),
+        }
+    }
 }
 
 #[derive(Debug, clap::Parser)]
@@ -40,6 +54,8 @@ pub enum Cli {
 #[derive(Debug, clap::Args)]
 #[command(author, version, about, long_about = None)]
 pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
     /// Override the location of the input file
     #[arg(short, long, default_value = "Cargo.lock")]
     pub input: PathBuf,
@@ -49,24 +65,36 @@ pub struct Args {
     /// Don't show any results, conflicts with 'verbose'
     #[arg(short, long, conflicts_with = "verbose")]
     pub quiet: bool,
-    /// The score required to pass the test.
-    #[arg(short, long, default_value_t = 80)]
-    pub score: u64,
-    /// Which score to test.
+    /// The score required to pass the test, optionally prefixed with a score type and followed by a score to warn below (e.g. `licensed=60:80`, can be used multiple times).
+    #[arg(short, long, default_value = "80")]
+    pub score: Vec<ScoreArg>,
+    /// Which score to test, if the score requirement doesn't name one.
     #[arg(short = 't', long, value_enum, default_value_t = ScoreType::Effective)]
     pub score_type: ScoreType,
+    /// Override the required score for crates matching a selector (e.g. `winapi-*=30`, can be used multiple times).
+    #[arg(long = "score-override")]
+    pub score_overrides: Vec<ScoreOverride>,
     /// Show only failed dependencies.
     #[arg(short = 'f', long)]
     pub failed: bool,
-    /// List the dependencies to exclude completely.
+    /// List the dependencies to exclude completely (glob or `regex:` pattern, optionally followed by `@<version-req>`).
     #[arg(short = 'x', long)]
-    pub exclude: Vec<String>,
-    /// List the dependencies to ignore when testing.
+    pub exclude: Vec<Selector>,
+    /// List the dependencies to ignore when testing (glob or `regex:` pattern, optionally followed by `@<version-req>`).
     #[arg(short = 'n', long)]
-    pub ignore: Vec<String>,
+    pub ignore: Vec<Selector>,
     /// Output format
     #[arg(short = 'o', long, value_enum, default_value_t = OutputFormat::Text)]
     pub output_format: OutputFormat,
+    /// Additionally write the report to a file, in the given format (e.g. `json=report.json`, can be used multiple times).
+    #[arg(long = "output", value_name = "FORMAT=PATH")]
+    pub outputs: Vec<OutputFile>,
+    /// Don't emit annotations and the job summary, when running in GitHub Actions.
+    #[arg(long)]
+    pub no_github_actions: bool,
+    /// The template to render, when using the template output format.
+    #[arg(long)]
+    pub template: Option<PathBuf>,
     /// Add a link to clearly defined.
     #[arg(short, long)]
     pub link: bool,
@@ -82,6 +110,198 @@ pub struct Args {
     /// Pass if a dependency has at least one of the approved licenses (can be used multiple times).
     #[arg(short = 'L', long = "approve")]
     pub approved_licenses: Vec<LicenseName>,
+    /// Pass if a dependency has a license of the category, optionally limited to crates matching a selector (e.g. `weak-copyleft:webpki-roots`, can be used multiple times).
+    #[arg(long = "allow-category")]
+    pub allowed_categories: Vec<CategoryRule>,
+    /// Fail if a dependency requires a license of the category, optionally limited to crates matching a selector (can be used multiple times).
+    #[arg(long = "deny-category")]
+    pub denied_categories: Vec<CategoryRule>,
+    /// A JSON file, mapping license categories to SPDX license ids, overriding the built-in categories.
+    #[arg(long)]
+    pub license_categories: Option<PathBuf>,
+    /// Check that the declared license agrees with the crate metadata, from `crates-io` (the default) or a directory of unpacked crates.
+    #[arg(long, num_args = 0..=1, default_missing_value = "crates-io", value_name = "SOURCE")]
+    pub check_metadata: Option<MetadataSource>,
+    /// Fail on warnings.
+    #[arg(long)]
+    pub deny_warnings: bool,
+    /// Only fail for failures which are not recorded in the baseline file.
+    #[arg(long)]
+    pub baseline: Option<PathBuf>,
+    /// Record the current failures in a baseline file.
+    #[arg(long)]
+    pub write_baseline: Option<PathBuf>,
+    /// A configuration file (TOML), see the README for the available settings.
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+    /// The base URL of the ClearlyDefined API, e.g. of a mirror.
+    #[arg(long, value_name = "URL")]
+    pub api_url: Option<String>,
+    /// A file containing the token to authenticate with the API (the token can also be provided using `CLEARLYDEFINED_TOKEN`).
+    #[arg(long, value_name = "FILE")]
+    pub credentials: Option<PathBuf>,
+    /// The proxy to use for HTTP requests (overrides the configuration file).
+    #[arg(long, value_name = "URL")]
+    pub proxy: Option<String>,
+    /// An additional trusted root certificate, in PEM format (can be used multiple times).
+    #[arg(long = "ca-certificate", value_name = "FILE")]
+    pub ca_certificates: Vec<PathBuf>,
+    /// A client certificate, in PEM format, for mutual TLS (requires `--client-key`).
+    #[arg(long, value_name = "FILE", requires = "client_key")]
+    pub client_certificate: Option<PathBuf>,
+    /// The private key of the client certificate, in PKCS#8 PEM format.
+    #[arg(long, value_name = "FILE", requires = "client_certificate")]
+    pub client_key: Option<PathBuf>,
+    /// The timeout of HTTP requests, in seconds.
+    #[arg(long, value_name = "SECONDS")]
+    pub timeout: Option<u64>,
+    /// The timeout of connecting to a server, in seconds.
+    #[arg(long, value_name = "SECONDS")]
+    pub connect_timeout: Option<u64>,
+    /// The User-Agent of HTTP requests.
+    #[arg(long)]
+    pub user_agent: Option<String>,
+    /// Only use the definitions of a bundle, created by the `fetch` command, without any network access (`--check-metadata` requires a directory then).
+    #[arg(long, value_name = "BUNDLE", conflicts_with = "cache")]
+    pub definitions: Option<PathBuf>,
+    /// Look up the curations which were submitted upstream for failed dependencies, and show the pull requests.
+    #[arg(long, conflicts_with = "definitions")]
+    pub pending_curations: bool,
+    /// A file of local curations (ClearlyDefined curation YAML), overriding the fetched definitions (can be used multiple times).
+    #[arg(long, value_name = "FILE")]
+    pub curations: Vec<PathBuf>,
+    /// Cache the fetched definitions in a directory, and use them instead of fetching them again.
+    #[arg(long, value_name = "DIR")]
+    pub cache: Option<PathBuf>,
+    /// A directory of definitions (`<name>/<version>.json`), taking precedence over the fetched definitions.
+    #[arg(long, value_name = "DIR")]
+    pub fixtures: Option<PathBuf>,
+    /// Fail if the declared license changed, compared to the previous lockfile, either a path or `<git-rev>:<path>` (defaults to the versions recorded in the baseline).
+    #[arg(long)]
+    pub previous: Option<LockfileSource>,
+}
+
+#[derive(Debug, clap::Subcommand)]
+pub enum Command {
+    /// Compare the dependencies of two lockfiles.
+    Diff(DiffArgs),
+    /// Download the definitions of all dependencies into a bundle, for use with `--definitions`.
+    Fetch(FetchArgs),
+    /// Create a curation of the declared license of a crate, and optionally submit it.
+    Curate(CurateArgs),
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum CurationFormat {
+    Yaml,
+    Json,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct CurateArgs {
+    /// The name of the crate.
+    pub name: String,
+    /// The version of the crate.
+    pub version: Version,
+    /// The correct declared license, as SPDX expression.
+    #[arg(short, long)]
+    pub license: String,
+    /// The format of the curation.
+    #[arg(short, long, value_enum, default_value_t = CurationFormat::Yaml)]
+    pub format: CurationFormat,
+    /// Write the curation to a file, instead of the standard output.
+    #[arg(short, long, conflicts_with = "submit")]
+    pub output: Option<PathBuf>,
+    /// Submit the curation to ClearlyDefined (or the configured API), creating a pull request.
+    #[arg(long)]
+    pub submit: bool,
+    /// The summary of the submitted curation.
+    #[arg(long, requires = "submit")]
+    pub summary: Option<String>,
+    /// The details of the submitted curation, e.g. where the license can be found.
+    #[arg(long, requires = "submit")]
+    pub details: Option<String>,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct FetchArgs {
+    /// The file to write the definitions to.
+    #[arg(short, long, default_value = "definitions.json")]
+    pub output: PathBuf,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct DiffArgs {
+    /// The old lockfile, either a path or `<git-rev>:<path>`.
+    pub old: LockfileSource,
+    /// The new lockfile, either a path or `<git-rev>:<path>`, defaults to the input file.
+    pub new: Option<LockfileSource>,
+}
+
+impl Args {
+    /// The options of the report, as provided on the command line.
+    pub fn report_options(&self) -> Options {
+        Options {
+            input: self.input.clone(),
+            lax: self.lax,
+            link: self.link,
+            template: self.template.clone(),
+            score_overrides: self.score_overrides.clone(),
+            approve_all: self.approve_all,
+            approve_osi: self.approve_osi,
+            approved_licenses: self.approved_licenses.iter().map(|name| name.0).collect(),
+            allowed_categories: self.allowed_categories.clone(),
+            denied_categories: self.denied_categories.clone(),
+            deny_warnings: self.deny_warnings,
+            exclude: self.exclude.clone(),
+            ignore: self.ignore.clone(),
+        }
+    }
+
+    /// The score requirements, with the default score type applied.
+    pub fn score_requirements(&self) -> Vec<ScoreRequirement> {
+        self.score
+            .iter()
+            .map(|s| ScoreRequirement {
+                score_type: s.score_type.unwrap_or(self.score_type),
+                score: s.score,
+                warn: s.warn,
+            })
+            .collect()
+    }
+}
+
+/// A score requirement, as provided on the command line.
+#[derive(Debug, Clone)]
+pub struct ScoreArg {
+    score_type: Option<ScoreType>,
+    score: u64,
+    warn: Option<u64>,
+}
+
+impl FromStr for ScoreArg {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (score_type, score) = match s.split_once('=') {
+            Some((score_type, score)) => (
+                Some(ScoreType::from_str(score_type.trim(), true).map_err(|e| anyhow!(e))?),
+                score,
+            ),
+            None => (None, s),
+        };
+
+        let (score, warn) = match score.split_once(':') {
+            Some((score, warn)) => (score, Some(warn.trim().parse()?)),
+            None => (score, None),
+        };
+
+        Ok(ScoreArg {
+            score_type,
+            score: score.trim().parse()?,
+            warn,
+        })
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -107,4 +327,38 @@ mod tests {
         use clap::CommandFactory;
         Cli::command().debug_assert();
     }
+
+    #[test]
+    fn parse_score() {
+        use clap::Parser;
+        let Cli::Clearlydefined(args) = Cli::try_parse_from([
+            "cargo",
+            "clearlydefined",
+            "-t",
+            "licensed",
+            "-s",
+            "70",
+            "-s",
+            "effective=60:80",
+        ])
+        .unwrap();
+        let req = args.score_requirements();
+
+        assert_eq!(req.len(), 2);
+        assert_eq!((req[0].score_type, req[0].score), (ScoreType::Licensed, 70));
+        assert_eq!(
+            (req[1].score_type, req[1].score),
+            (ScoreType::Effective, 60)
+        );
+    }
+
+    #[test]
+    fn parse_output() {
+        let output = OutputFile::from_str("SARIF=target/report.sarif").unwrap();
+        assert!(matches!(output.format, OutputFormat::Sarif));
+        assert_eq!(output.path, PathBuf::from("target/report.sarif"));
+
+        assert!(OutputFile::from_str("report.json").is_err());
+        assert!(OutputFile::from_str("yaml=report.yaml").is_err());
+    }
 }
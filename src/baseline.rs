@@ -0,0 +1,191 @@
+/*
+ * Copyright (c) 2020 Red Hat Inc.
+ *
+ * See the NOTICE file(s) distributed with this work for additional
+ * information regarding copyright ownership.
+ *
+ * This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License 2.0 which is available at
+ * http://www.eclipse.org/legal/epl-2.0
+ *
+ * SPDX-License-Identifier: EPL-2.0
+ */
+
+use crate::data::Dependency;
+use crate::diff::collect_versions;
+use crate::policy::ScoreRequirement;
+use anyhow::{Context, Result};
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+/// A set of known failures, which should not fail the run.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Baseline {
+    pub failures: Vec<Failure>,
+    /// The locked versions, used to detect changes.
+    #[serde(default)]
+    pub packages: Vec<Package>,
+}
+
+/// A locked version of a crate.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Package {
+    pub name: String,
+    pub version: Version,
+}
+
+/// A single failure of a dependency.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Failure {
+    pub name: String,
+    pub version: Version,
+    pub reason: String,
+}
+
+impl Baseline {
+    /// Create a baseline from the current failures of the dependencies.
+    pub fn new(dependencies: &[Dependency], score_requirements: &[ScoreRequirement]) -> Self {
+        Baseline {
+            failures: dependencies
+                .iter()
+                .flat_map(|dep| failures(dep, score_requirements))
+                .collect(),
+            packages: dependencies
+                .iter()
+                .map(|dep| Package {
+                    name: dep.name.clone(),
+                    version: dep.version.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    /// The locked versions of each crate, recorded in the baseline.
+    pub fn versions(&self) -> BTreeMap<String, Vec<Version>> {
+        collect_versions(self.packages.iter().map(|p| (p.name.as_str(), &p.version)))
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = File::open(path)
+            .with_context(|| format!("Failed to open baseline: {}", path.display()))?;
+        Ok(serde_json::from_reader(BufReader::new(file))?)
+    }
+
+    pub fn store(&self, path: &Path) -> Result<()> {
+        let file = File::create(path)
+            .with_context(|| format!("Failed to create baseline: {}", path.display()))?;
+        serde_json::to_writer_pretty(BufWriter::new(file), self)?;
+        Ok(())
+    }
+
+    /// Check if the dependency failed, but only with failures recorded in the baseline.
+    pub fn covers(&self, dependency: &Dependency, score_requirements: &[ScoreRequirement]) -> bool {
+        let failures = failures(dependency, score_requirements);
+        !failures.is_empty() && failures.iter().all(|f| self.failures.contains(f))
+    }
+
+    /// Get all failures of the baseline which are not present anymore.
+    pub fn resolved<'a>(
+        &'a self,
+        dependencies: &[Dependency],
+        score_requirements: &[ScoreRequirement],
+    ) -> Vec<&'a Failure> {
+        let current = Baseline::new(dependencies, score_requirements);
+        self.failures
+            .iter()
+            .filter(|f| !current.failures.contains(f))
+            .collect()
+    }
+}
+
+/// Collect the failures of a dependency.
+fn failures(dependency: &Dependency, score_requirements: &[ScoreRequirement]) -> Vec<Failure> {
+    let failure = |reason: String| Failure {
+        name: dependency.name.clone(),
+        version: dependency.version.clone(),
+        reason,
+    };
+
+    let mut result = Vec::new();
+
+    if dependency.passed_license.is_fail() {
+        result.push(failure("license".into()));
+    }
+
+    if dependency.passed_license_change.is_fail() {
+        result.push(failure("license change".into()));
+    }
+
+    if dependency.passed_metadata.is_fail() {
+        result.push(failure("metadata".into()));
+    }
+
+    for (outcome, req) in dependency.passed_score.iter().zip(score_requirements) {
+        if outcome.is_fail() {
+            result.push(failure(format!("{} score", req.score_type)));
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::Outcome;
+    use crate::policy::ScoreType;
+
+    fn dependency(name: &str, license: Outcome, score: Outcome) -> Dependency {
+        let mut dep = Dependency::new(name.into(), Version::new(1, 0, 0));
+        dep.passed_license = license;
+        dep.passed_score = vec![score];
+        dep
+    }
+
+    #[test]
+    fn baselined_failures() {
+        let reqs = [ScoreRequirement {
+            score_type: ScoreType::Effective,
+            score: 80,
+            warn: None,
+        }];
+
+        let baseline = Baseline::new(
+            &[
+                dependency("a", Outcome::Fail, Outcome::Pass),
+                dependency("b", Outcome::Fail, Outcome::Pass),
+                dependency("c", Outcome::Fail, Outcome::Pass),
+                dependency("d", Outcome::Pass, Outcome::Pass),
+            ],
+            &reqs,
+        );
+        assert_eq!(baseline.failures.len(), 3);
+
+        let current = [
+            // only the recorded failure
+            dependency("a", Outcome::Fail, Outcome::Pass),
+            // an additional failure
+            dependency("b", Outcome::Fail, Outcome::Fail),
+            // fixed
+            dependency("c", Outcome::Pass, Outcome::Pass),
+            // passing, nothing to cover
+            dependency("d", Outcome::Pass, Outcome::Pass),
+        ];
+
+        assert!(baseline.covers(&current[0], &reqs));
+        assert!(!baseline.covers(&current[1], &reqs));
+        assert!(!baseline.covers(&current[2], &reqs));
+        assert!(!baseline.covers(&current[3], &reqs));
+
+        let resolved: Vec<_> = baseline
+            .resolved(&current, &reqs)
+            .into_iter()
+            .map(|f| (f.name.as_str(), f.reason.as_str()))
+            .collect();
+        assert_eq!(resolved, vec![("c", "license")]);
+    }
+}
@@ -0,0 +1,150 @@
+/*
+ * Copyright (c) 2020 Red Hat Inc.
+ *
+ * See the NOTICE file(s) distributed with this work for additional
+ * information regarding copyright ownership.
+ *
+ * This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License 2.0 which is available at
+ * http://www.eclipse.org/legal/epl-2.0
+ *
+ * SPDX-License-Identifier: EPL-2.0
+ */
+
+use anyhow::{Context, Result};
+use spdx::{LicenseItem, LicenseReq};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+pub const PERMISSIVE: &str = "permissive";
+pub const WEAK_COPYLEFT: &str = "weak-copyleft";
+pub const STRONG_COPYLEFT: &str = "strong-copyleft";
+
+const BUILTIN_PERMISSIVE: &[&str] = &[
+    "0BSD",
+    "Apache-1.1",
+    "Apache-2.0",
+    "BSD-1-Clause",
+    "BSD-2-Clause",
+    "BSD-3-Clause",
+    "BSL-1.0",
+    "CC0-1.0",
+    "ISC",
+    "MIT",
+    "MIT-0",
+    "NCSA",
+    "PostgreSQL",
+    "Unicode-3.0",
+    "Unicode-DFS-2016",
+    "Unlicense",
+    "X11",
+    "Zlib",
+];
+
+const BUILTIN_WEAK_COPYLEFT: &[&str] = &[
+    "CDDL-1.0", "CDDL-1.1", "CPL-1.0", "EPL-1.0", "EPL-2.0", "LGPL-2.0", "LGPL-2.1", "LGPL-3.0",
+    "MPL-1.1", "MPL-2.0", "MS-RL",
+];
+
+const BUILTIN_STRONG_COPYLEFT: &[&str] = &[
+    "AGPL-1.0", "AGPL-3.0", "EUPL-1.1", "EUPL-1.2", "GPL-1.0", "GPL-2.0", "GPL-3.0", "OSL-3.0",
+    "RPL-1.5", "SSPL-1.0",
+];
+
+/// A mapping of license categories to the SPDX license ids in that category.
+///
+/// License ids are compared without the `-only` and `-or-later` suffixes, so `GPL-2.0` covers
+/// `GPL-2.0-only` and `GPL-2.0-or-later` as well.
+#[derive(Debug, Clone)]
+pub struct Categories {
+    categories: BTreeMap<String, Vec<String>>,
+}
+
+impl Default for Categories {
+    fn default() -> Self {
+        let mut categories = BTreeMap::new();
+        for (name, licenses) in [
+            (PERMISSIVE, BUILTIN_PERMISSIVE),
+            (WEAK_COPYLEFT, BUILTIN_WEAK_COPYLEFT),
+            (STRONG_COPYLEFT, BUILTIN_STRONG_COPYLEFT),
+        ] {
+            categories.insert(
+                name.to_string(),
+                licenses.iter().map(|l| l.to_string()).collect(),
+            );
+        }
+        Categories { categories }
+    }
+}
+
+impl Categories {
+    /// Load the built-in categories, overridden by the categories of the mapping file.
+    ///
+    /// The file is a JSON object, mapping the name of a category to a list of SPDX license ids.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let mut result = Self::default();
+
+        if let Some(path) = path {
+            let file = File::open(path).with_context(|| {
+                format!("Failed to open license categories: {}", path.display())
+            })?;
+            let categories: BTreeMap<String, Vec<String>> =
+                serde_json::from_reader(BufReader::new(file))?;
+            result.categories.extend(categories);
+        }
+
+        Ok(result)
+    }
+
+    pub fn exists(&self, category: &str) -> bool {
+        self.categories.contains_key(category)
+    }
+
+    /// Get the category names, in alphabetical order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.categories.keys().map(|k| k.as_str())
+    }
+
+    /// Check if the license is part of the category.
+    pub fn contains(&self, category: &str, req: &LicenseReq) -> bool {
+        let licenses = match self.categories.get(category) {
+            Some(licenses) => licenses,
+            None => return false,
+        };
+
+        match &req.license {
+            LicenseItem::Spdx { id, .. } => {
+                let base = id
+                    .name
+                    .strip_suffix("-only")
+                    .or_else(|| id.name.strip_suffix("-or-later"))
+                    .unwrap_or(id.name);
+                licenses.iter().any(|l| l == id.name || l == base)
+            }
+            LicenseItem::Other { lic_ref, .. } => {
+                let name = format!("LicenseRef-{}", lic_ref);
+                licenses.contains(&name)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spdx::Expression;
+
+    #[test]
+    fn builtin_categories() {
+        let categories = Categories::default();
+        let expression = Expression::parse("MIT OR GPL-3.0-or-later OR MPL-2.0").unwrap();
+        let reqs: Vec<_> = expression.requirements().map(|r| &r.req).collect();
+
+        assert!(categories.contains(PERMISSIVE, reqs[0]));
+        assert!(categories.contains(STRONG_COPYLEFT, reqs[1]));
+        assert!(categories.contains(WEAK_COPYLEFT, reqs[2]));
+        assert!(!categories.contains(PERMISSIVE, reqs[1]));
+    }
+}
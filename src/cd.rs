@@ -11,32 +11,209 @@
  * SPDX-License-Identifier: EPL-2.0
  */
 
-use crate::data::{ClearlyDefined, Dependency, License};
+use crate::data::{ClearlyDefined, Dependency, License, PreviousVersion, UpstreamCuration};
 
+use crate::curation::CURATED;
+use crate::source::{DefinitionSource, HttpSource};
 use anyhow::Result;
-use reqwest::Client;
+use futures::{stream, StreamExt, TryStreamExt};
+use semver::Version;
+use serde_json::Value;
+use std::collections::BTreeMap;
 
+/// Lookup the information of all dependencies.
+pub async fn lookup_all(
+    source: &dyn DefinitionSource,
+    dependencies: Vec<Dependency>,
+) -> Result<Vec<Dependency>> {
+    let result = stream::iter(dependencies.into_iter().map(Ok))
+        .and_then(|d| lookup_clearlydefined(source, d))
+        .map(|s| {
+            if let Ok(dep) = &s {
+                log::info!("Processed: {}/{}", dep.name, dep.version);
+            }
+
+            s
+        })
+        .try_collect::<Vec<_>>()
+        .await?;
+
+    log::info!("Processed all dependencies");
+
+    Ok(result)
+}
+
+/// Lookup the information of the previously locked versions of the dependencies.
+///
+/// If a dependency was previously locked with multiple versions, the closest older version is
+/// used. Dependencies which were not locked before are skipped.
+pub async fn lookup_previous(
+    source: &dyn DefinitionSource,
+    dependencies: &mut [Dependency],
+    previous: &BTreeMap<String, Vec<Version>>,
+) -> Result<()> {
+    for dep in dependencies {
+        let versions = match previous.get(&dep.name) {
+            Some(versions) => versions,
+            None => continue,
+        };
+
+        if versions.contains(&dep.version) {
+            dep.previous = Some(PreviousVersion {
+                version: dep.version.clone(),
+                declared_license: dep.declared_license().map(Into::into),
+            });
+            continue;
+        }
+
+        let version = versions
+            .iter()
+            .filter(|v| *v < &dep.version)
+            .max()
+            .or_else(|| versions.iter().min());
+
+        if let Some(version) = version {
+            let prev =
+                lookup_clearlydefined(source, Dependency::new(dep.name.clone(), version.clone()))
+                    .await?;
+            log::info!("Processed previous version: {}/{}", prev.name, prev.version);
+
+            dep.previous = Some(PreviousVersion {
+                version: prev.version.clone(),
+                declared_license: prev.declared_license().map(Into::into),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Lookup the curations, submitted upstream, of the dependencies.
+pub async fn lookup_curations(
+    source: &HttpSource,
+    dependencies: impl IntoIterator<Item = &mut Dependency>,
+) -> Result<()> {
+    for dep in dependencies {
+        let curations = source.curations(&dep.name, &dep.version).await?;
+        dep.curations = parse_curations(&curations);
+        log::info!("Processed curations: {}/{}", dep.name, dep.version);
+    }
+
+    Ok(())
+}
+
+/// Parse the pull requests of the contributions, skipping the ones which were closed without
+/// being merged.
+fn parse_curations(curations: &Value) -> Vec<UpstreamCuration> {
+    let contributions = match curations["contributions"].as_array() {
+        Some(contributions) => contributions,
+        None => return vec![],
+    };
+
+    contributions
+        .iter()
+        .filter_map(|contribution| {
+            let pr = &contribution["pr"];
+            let merged = !pr["merged_at"].is_null();
+            if pr["state"].as_str() != Some("open") && !merged {
+                return None;
+            }
+
+            let number = pr["number"].as_u64()?;
+            Some(UpstreamCuration {
+                number,
+                title: pr["title"].as_str().unwrap_or_default().into(),
+                url: format!(
+                    "https://github.com/clearlydefined/curated-data/pull/{}",
+                    number
+                ),
+                merged,
+            })
+        })
+        .collect()
+}
+
+/// Lookup the definition of a dependency.
 pub async fn lookup_clearlydefined(
-    client: &Client,
+    source: &dyn DefinitionSource,
     mut dependency: Dependency,
 ) -> Result<Dependency> {
-    let url = format!(
-        "https://api.clearlydefined.io/definitions/crate/cratesio/-/{}/{}",
-        dependency.name, dependency.version
-    );
+    dependency.clearly_defined = source
+        .definition(&dependency.name, &dependency.version)
+        .await?
+        .map(|def| parse_definition(&def))
+        .transpose()?;
 
-    let def: serde_json::Value = client.get(&url).send().await?.json().await?;
+    Ok(dependency)
+}
 
+/// Parse the raw definition, as returned by the ClearlyDefined API.
+pub fn parse_definition(def: &Value) -> Result<ClearlyDefined> {
     let license = def["licensed"]["declared"]
         .as_str()
         .map(|s| License::new(s.into()))
         .transpose()?;
 
-    dependency.clearly_defined = Some(ClearlyDefined::new(
+    let mut score_details = Vec::new();
+    for (section, scores) in [
+        ("described", &["total", "date", "source"][..]),
+        (
+            "licensed",
+            &[
+                "total",
+                "declared",
+                "discovered",
+                "consistency",
+                "spdx",
+                "texts",
+            ][..],
+        ),
+    ] {
+        for score in scores {
+            if let Some(value) = def[section]["score"][score].as_u64() {
+                score_details.push((format!("{}.{}", section, score), value));
+            }
+        }
+    }
+
+    let mut result = ClearlyDefined::new(
         license,
         def["scores"]["effective"].as_u64().unwrap_or(0),
         def["licensed"]["score"]["total"].as_u64().unwrap_or(0),
-    ));
+        score_details,
+    );
+
+    if let Some(curated) = def[CURATED].as_array() {
+        result.curated = curated
+            .iter()
+            .filter_map(|field| field.as_str().map(Into::into))
+            .collect();
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn pending_curations() {
+        let curations = json!({
+            "curations": {},
+            "contributions": [
+                { "pr": { "number": 1, "title": "Open", "state": "open", "merged_at": null } },
+                { "pr": { "number": 2, "title": "Merged", "state": "closed", "merged_at": "2020-01-01T00:00:00Z" } },
+                { "pr": { "number": 3, "title": "Rejected", "state": "closed", "merged_at": null } }
+            ]
+        });
+
+        let result: Vec<_> = parse_curations(&curations)
+            .into_iter()
+            .map(|c| (c.number, c.merged))
+            .collect();
 
-    Ok(dependency.clone())
+        assert_eq!(result, vec![(1, false), (2, true)]);
+    }
 }
@@ -0,0 +1,252 @@
+/*
+ * Copyright (c) 2020 Red Hat Inc.
+ *
+ * See the NOTICE file(s) distributed with this work for additional
+ * information regarding copyright ownership.
+ *
+ * This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License 2.0 which is available at
+ * http://www.eclipse.org/legal/epl-2.0
+ *
+ * SPDX-License-Identifier: EPL-2.0
+ */
+
+//! The configuration file, for settings which don't change between runs.
+
+use crate::args::Args;
+use crate::source::{HttpSource, API_URL};
+use anyhow::{anyhow, Context, Result};
+use reqwest::{Certificate, Client, Identity, Proxy};
+use serde::Deserialize;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+/// The environment variable providing the API token.
+pub const TOKEN_ENV: &str = "CLEARLYDEFINED_TOKEN";
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub api: ApiConfig,
+    pub http: HttpConfig,
+}
+
+/// The settings of the ClearlyDefined API.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ApiConfig {
+    /// The base URL of the API, e.g. of a mirror.
+    pub url: Option<String>,
+    /// A file containing the token to authenticate with. The token itself is never part of the
+    /// configuration.
+    pub credentials: Option<PathBuf>,
+}
+
+/// The settings of the HTTP client.
+///
+/// Relative paths are resolved against the directory of the configuration file.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct HttpConfig {
+    /// The proxy to use for all requests.
+    pub proxy: Option<String>,
+    /// Additional trusted root certificates (PEM).
+    pub ca_certificates: Vec<PathBuf>,
+    /// The client certificate (PEM), for mutual TLS.
+    pub client_certificate: Option<PathBuf>,
+    /// The private key (PKCS#8 PEM) of the client certificate.
+    pub client_key: Option<PathBuf>,
+    /// The timeout of a request, in seconds.
+    pub timeout: Option<u64>,
+    /// The timeout of connecting to a server, in seconds.
+    pub connect_timeout: Option<u64>,
+    pub user_agent: Option<String>,
+}
+
+impl Config {
+    /// Load the configuration file, or use the default configuration if there is none.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let path = match path {
+            Some(path) => path,
+            None => return Ok(Self::default()),
+        };
+
+        let mut config: Config = toml::from_str(
+            &fs::read_to_string(path)
+                .with_context(|| format!("Failed to read configuration: {}", path.display()))?,
+        )
+        .with_context(|| format!("Failed to parse configuration: {}", path.display()))?;
+
+        if let Some(dir) = path.parent() {
+            config.api.resolve(dir);
+            config.http.resolve(dir);
+        }
+
+        Ok(config)
+    }
+
+    /// Load the configuration file of the arguments, overridden by the command line arguments.
+    pub fn from_args(args: &Args) -> Result<Self> {
+        let mut config = Self::load(args.config.as_deref())?;
+        config.api.merge(args);
+        config.http.merge(args);
+        Ok(config)
+    }
+}
+
+impl ApiConfig {
+    fn resolve(&mut self, dir: &Path) {
+        if let Some(path) = &mut self.credentials {
+            *path = dir.join(&path);
+        }
+    }
+
+    fn merge(&mut self, args: &Args) {
+        if let Some(url) = &args.api_url {
+            self.url = Some(url.clone());
+        }
+        if let Some(credentials) = &args.credentials {
+            self.credentials = Some(credentials.clone());
+        }
+    }
+
+    /// The HTTP source of definitions, using the configured API and token.
+    pub fn source(&self, client: Client) -> Result<HttpSource> {
+        let source = HttpSource::with_url(client, self.url.as_deref().unwrap_or(API_URL));
+        match self.token()? {
+            Some(token) => source.with_token(&token),
+            None => Ok(source),
+        }
+    }
+
+    /// The API token, from the environment or the credentials file.
+    pub fn token(&self) -> Result<Option<String>> {
+        if let Some(token) = env::var(TOKEN_ENV).ok().filter(|t| !t.is_empty()) {
+            return Ok(Some(token));
+        }
+
+        match &self.credentials {
+            Some(path) => {
+                let token = fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read credentials: {}", path.display()))?;
+                Ok(Some(token.trim().to_string()))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+impl HttpConfig {
+    fn resolve(&mut self, dir: &Path) {
+        for path in self
+            .ca_certificates
+            .iter_mut()
+            .chain(&mut self.client_certificate)
+            .chain(&mut self.client_key)
+        {
+            *path = dir.join(&path);
+        }
+    }
+
+    /// Override the settings of the configuration file with the command line arguments.
+    fn merge(&mut self, args: &Args) {
+        if let Some(proxy) = &args.proxy {
+            self.proxy = Some(proxy.clone());
+        }
+        self.ca_certificates.extend(args.ca_certificates.clone());
+        if let Some(certificate) = &args.client_certificate {
+            self.client_certificate = Some(certificate.clone());
+        }
+        if let Some(key) = &args.client_key {
+            self.client_key = Some(key.clone());
+        }
+        if let Some(timeout) = args.timeout {
+            self.timeout = Some(timeout);
+        }
+        if let Some(timeout) = args.connect_timeout {
+            self.connect_timeout = Some(timeout);
+        }
+        if let Some(user_agent) = &args.user_agent {
+            self.user_agent = Some(user_agent.clone());
+        }
+    }
+
+    /// Create the HTTP client.
+    pub fn client(&self) -> Result<Client> {
+        let read = |path: &Path| {
+            fs::read(path).with_context(|| format!("Failed to read: {}", path.display()))
+        };
+
+        let mut builder =
+            Client::builder().user_agent(self.user_agent.as_deref().unwrap_or(USER_AGENT));
+
+        if let Some(proxy) = &self.proxy {
+            builder = builder
+                .proxy(Proxy::all(proxy).with_context(|| format!("Invalid proxy: {}", proxy))?);
+        }
+
+        for path in &self.ca_certificates {
+            for certificate in Certificate::from_pem_bundle(&read(path)?)
+                .with_context(|| format!("Invalid CA certificate: {}", path.display()))?
+            {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+
+        match (&self.client_certificate, &self.client_key) {
+            (Some(certificate), Some(key)) => {
+                let identity = Identity::from_pkcs8_pem(&read(certificate)?, &read(key)?)
+                    .with_context(|| {
+                        format!("Invalid client certificate: {}", certificate.display())
+                    })?;
+                builder = builder.identity(identity);
+            }
+            (None, None) => {}
+            _ => {
+                return Err(anyhow!(
+                    "A client certificate requires both the certificate and the key"
+                ))
+            }
+        }
+
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(Duration::from_secs(timeout));
+        }
+        if let Some(timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(Duration::from_secs(timeout));
+        }
+
+        Ok(builder.build()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_config() {
+        let mut config: Config = toml::from_str(
+            r#"
+[http]
+proxy = "http://proxy:3128"
+ca-certificates = ["corporate.pem"]
+timeout = 30
+"#,
+        )
+        .unwrap();
+        config.http.resolve(Path::new("/etc/clearlydefined"));
+
+        assert_eq!(config.http.proxy.as_deref(), Some("http://proxy:3128"));
+        assert_eq!(
+            config.http.ca_certificates,
+            vec![PathBuf::from("/etc/clearlydefined/corporate.pem")]
+        );
+        assert_eq!(config.http.timeout, Some(30));
+        assert!(toml::from_str::<Config>("[http]\nproxi = \"x\"").is_err());
+    }
+}
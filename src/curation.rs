@@ -0,0 +1,289 @@
+/*
+ * Copyright (c) 2020 Red Hat Inc.
+ *
+ * See the NOTICE file(s) distributed with this work for additional
+ * information regarding copyright ownership.
+ *
+ * This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License 2.0 which is available at
+ * http://www.eclipse.org/legal/epl-2.0
+ *
+ * SPDX-License-Identifier: EPL-2.0
+ */
+
+//! ClearlyDefined curations, correcting the data of a definition.
+
+use crate::args::{Args, CurateArgs, CurationFormat};
+use crate::config::Config;
+use crate::data::License;
+use crate::source::DefinitionSource;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::ExitCode;
+
+const TYPE: &str = "crate";
+const PROVIDER: &str = "cratesio";
+
+/// The field of a definition, listing the fields which were overridden by a local curation.
+pub const CURATED: &str = "_curated";
+
+/// The coordinates of a curation, without the revision.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Coordinates {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub provider: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+    pub name: String,
+}
+
+/// A curation patch, in the format of the ClearlyDefined curated data: the fields to override,
+/// per revision.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Curation {
+    pub coordinates: Coordinates,
+    pub revisions: BTreeMap<String, Value>,
+}
+
+impl Coordinates {
+    pub fn new(name: &str) -> Self {
+        Coordinates {
+            kind: TYPE.into(),
+            provider: PROVIDER.into(),
+            namespace: None,
+            name: name.into(),
+        }
+    }
+
+    /// Check if the coordinates refer to the crate.
+    pub fn is_crate(&self, name: &str) -> bool {
+        self.kind == TYPE
+            && self.provider == PROVIDER
+            && matches!(self.namespace.as_deref(), None | Some("-"))
+            && self.name == name
+    }
+}
+
+impl Curation {
+    /// A curation of the declared license of a crate.
+    pub fn declared_license(name: &str, version: &Version, license: &str) -> Self {
+        let mut revisions = BTreeMap::new();
+        revisions.insert(
+            version.to_string(),
+            json!({ "licensed": { "declared": license } }),
+        );
+
+        Curation {
+            coordinates: Coordinates::new(name),
+            revisions,
+        }
+    }
+}
+
+/// Merge the patch into the definition, recording the paths of the overridden fields.
+fn merge(definition: &mut Value, patch: &Value, path: &str, curated: &mut Vec<String>) {
+    if definition.is_null() && patch.is_object() {
+        *definition = json!({});
+    }
+
+    match (definition, patch) {
+        (Value::Object(definition), Value::Object(patch)) => {
+            for (key, value) in patch {
+                let path = match path {
+                    "" => key.clone(),
+                    path => format!("{}.{}", path, key),
+                };
+                merge(
+                    definition.entry(key.clone()).or_insert(Value::Null),
+                    value,
+                    &path,
+                    curated,
+                );
+            }
+        }
+        (definition, patch) => {
+            if definition != patch {
+                curated.push(path.into());
+            }
+            *definition = patch.clone();
+        }
+    }
+}
+
+/// Local curations, applied to the definitions of another source.
+pub struct CuratedSource {
+    curations: Vec<Curation>,
+    inner: Box<dyn DefinitionSource>,
+}
+
+impl CuratedSource {
+    pub fn new(curations: Vec<Curation>, inner: Box<dyn DefinitionSource>) -> Self {
+        CuratedSource { curations, inner }
+    }
+
+    /// Load the curations of a file, which may contain multiple YAML (or JSON) documents.
+    pub fn load(path: &Path) -> Result<Vec<Curation>> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read curations: {}", path.display()))?;
+
+        serde_yaml::Deserializer::from_str(&content)
+            .map(|document| {
+                Curation::deserialize(document)
+                    .with_context(|| format!("Failed to parse curations: {}", path.display()))
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl DefinitionSource for CuratedSource {
+    async fn definition(&self, name: &str, version: &Version) -> Result<Option<Value>> {
+        let mut definition = self.inner.definition(name, version).await?;
+        let version = version.to_string();
+
+        let patches = self
+            .curations
+            .iter()
+            .filter(|c| c.coordinates.is_crate(name))
+            .filter_map(|c| c.revisions.get(&version));
+
+        let mut curated = Vec::new();
+        for patch in patches {
+            merge(
+                definition.get_or_insert_with(|| json!({})),
+                patch,
+                "",
+                &mut curated,
+            );
+        }
+
+        if let (Some(definition), false) = (&mut definition, curated.is_empty()) {
+            log::info!(
+                "Locally curated {}/{}: {}",
+                name,
+                version,
+                curated.join(", ")
+            );
+            definition[CURATED] = json!(curated);
+        }
+
+        Ok(definition)
+    }
+}
+
+fn summary(curate_args: &CurateArgs, license: &str) -> String {
+    curate_args.summary.clone().unwrap_or_else(|| {
+        format!(
+            "Declared license of {} {} is {}",
+            curate_args.name, curate_args.version, license
+        )
+    })
+}
+
+pub async fn run(args: &Args, curate_args: &CurateArgs) -> Result<ExitCode> {
+    let license = License::new(curate_args.license.clone())?
+        .expression(args.lax)
+        .with_context(|| format!("Invalid SPDX expression: {}", curate_args.license))?
+        .to_string();
+
+    let curation = Curation::declared_license(&curate_args.name, &curate_args.version, &license);
+
+    if curate_args.submit {
+        let config = Config::from_args(args)?;
+        let client = config.http.client()?;
+        let source = config.api.source(client)?;
+
+        let contribution = json!({
+            "contributionInfo": {
+                "type": "incorrect",
+                "summary": summary(curate_args, &license),
+                "details": curate_args.details.clone().unwrap_or_default(),
+                "resolution": format!("Set the declared license to {}", license),
+                "removedDefinitions": false,
+            },
+            "patches": [curation],
+        });
+
+        let response = source.submit_curation(&contribution).await?;
+        let mut out = io::stdout();
+        match response["url"].as_str() {
+            Some(url) => writeln!(out, "Submitted curation: {}", url)?,
+            None => writeln!(out, "Submitted curation")?,
+        }
+
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let output = match curate_args.format {
+        CurationFormat::Yaml => serde_yaml::to_string(&curation)?,
+        CurationFormat::Json => serde_json::to_string_pretty(&curation)? + "\n",
+    };
+
+    match &curate_args.output {
+        Some(path) => {
+            fs::write(path, output)
+                .with_context(|| format!("Failed to write curation: {}", path.display()))?;
+            log::info!("Written curation: {}", path.display());
+        }
+        None => io::stdout().write_all(output.as_bytes())?,
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn declared_license_curation() {
+        let curation =
+            Curation::declared_license("foo", &Version::new(1, 2, 3), "MIT OR Apache-2.0");
+        let yaml = serde_yaml::to_string(&curation).unwrap();
+
+        assert_eq!(
+            yaml,
+            r#"coordinates:
+  type: crate
+  provider: cratesio
+  name: foo
+revisions:
+  1.2.3:
+    licensed:
+      declared: MIT OR Apache-2.0
+"#
+        );
+
+        let parsed: Curation = serde_yaml::from_str(&yaml).unwrap();
+        assert!(parsed.coordinates.is_crate("foo"));
+    }
+
+    #[test]
+    fn merge_curation() {
+        let mut definition = json!({
+            "licensed": { "declared": "MIT", "score": { "total": 50 } },
+            "scores": { "effective": 60 }
+        });
+        let mut curated = Vec::new();
+
+        merge(
+            &mut definition,
+            &json!({ "licensed": { "declared": "Apache-2.0", "score": { "total": 50 } } }),
+            "",
+            &mut curated,
+        );
+
+        assert_eq!(definition["licensed"]["declared"], "Apache-2.0");
+        assert_eq!(definition["licensed"]["score"]["total"], 50);
+        assert_eq!(definition["scores"]["effective"], 60);
+        assert_eq!(curated, vec!["licensed.declared".to_string()]);
+    }
+}
@@ -13,18 +13,41 @@
 
 use anyhow::{anyhow, Result};
 
-use crate::args::ScoreType;
+use crate::category::Categories;
+use crate::policy::{CategoryRule, ScoreType};
 use semver::Version;
-use spdx::{Expression, LicenseId, LicenseItem, ParseMode};
+use serde::Serialize;
+use spdx::expression::{ExprNode, Operator};
+use spdx::{Expression, LicenseId, LicenseItem, LicenseReq, ParseMode};
 use std::cmp::Ordering;
+use std::fmt;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Outcome {
     Pass,
+    /// Passed, but only due to an overridden requirement.
+    Override,
+    /// Passed, but with a warning.
+    Warn,
     Fail,
     Ignore,
 }
 
+impl Outcome {
+    pub fn is_fail(&self) -> bool {
+        matches!(self, Outcome::Fail)
+    }
+
+    /// Turn a warning into a failure.
+    pub fn deny_warning(self) -> Self {
+        match self {
+            Outcome::Warn => Outcome::Fail,
+            outcome => outcome,
+        }
+    }
+}
+
 impl From<bool> for Outcome {
     fn from(b: bool) -> Self {
         match b {
@@ -42,7 +65,86 @@ pub struct Dependency {
     pub clearly_defined: Option<ClearlyDefined>,
 
     pub passed_license: Outcome,
-    pub passed_score: Outcome,
+    /// The outcome of each of the score requirements.
+    pub passed_score: Vec<Outcome>,
+
+    /// The licenses chosen from the declared license, satisfying the license checks.
+    pub effective_license: Option<String>,
+
+    /// The reasons for failed tests and warnings.
+    pub reasons: Vec<Reason>,
+
+    /// The previously locked version, if known.
+    pub previous: Option<PreviousVersion>,
+    pub passed_license_change: Outcome,
+
+    /// The license information of the crate metadata, if checked.
+    pub metadata: Option<CrateMetadata>,
+    pub passed_metadata: Outcome,
+
+    /// The curations submitted upstream, if looked up.
+    pub curations: Vec<UpstreamCuration>,
+}
+
+/// The check a reason belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Check {
+    /// Notes about curations of the definition, not failing any check.
+    Curation,
+    License,
+    LicenseChange,
+    Score,
+    Metadata,
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Check::Curation => f.write_str("curation"),
+            Check::License => f.write_str("license"),
+            Check::LicenseChange => f.write_str("license-change"),
+            Check::Score => f.write_str("score"),
+            Check::Metadata => f.write_str("metadata"),
+        }
+    }
+}
+
+/// A reason for a failed test or a warning, produced by a check.
+#[derive(Debug, Clone)]
+pub struct Reason {
+    pub check: Check,
+    pub message: String,
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// A curation, submitted to ClearlyDefined as a pull request.
+#[derive(Debug, Clone, Serialize)]
+pub struct UpstreamCuration {
+    pub number: u64,
+    pub title: String,
+    pub url: String,
+    /// If the pull request was merged already, the definition gets updated once re-harvested.
+    pub merged: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct CrateMetadata {
+    /// The `license` field of the crate
+    pub license: Option<String>,
+    /// If the crate has a `license-file` field
+    pub license_file: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct PreviousVersion {
+    pub version: Version,
+    pub declared_license: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -50,6 +152,10 @@ pub struct ClearlyDefined {
     pub declared_license: Option<License>,
     effective_score: u64,
     licensed_score: u64,
+    /// The individual scores, contributing to the total scores.
+    pub score_details: Vec<(String, u64)>,
+    /// The fields which were overridden by a local curation (e.g. `licensed.declared`).
+    pub curated: Vec<String>,
 }
 
 impl ClearlyDefined {
@@ -57,11 +163,14 @@ impl ClearlyDefined {
         declared_license: Option<License>,
         effective_score: u64,
         licensed_score: u64,
+        score_details: Vec<(String, u64)>,
     ) -> Self {
         ClearlyDefined {
             declared_license,
             effective_score,
             licensed_score,
+            score_details,
+            curated: Vec::new(),
         }
     }
 
@@ -119,24 +228,133 @@ impl PartialOrd for Dependency {
 }
 
 pub trait LicenseCheck {
-    fn check(&self, expression: &Expression) -> Result<()>;
+    /// Check if a single license is acceptable for the dependency.
+    fn accepts(&self, dependency: &Dependency, license: &LicenseReq) -> bool;
+
+    /// Create the error, in case the expression is not acceptable.
+    fn reject(&self, expression: &Expression) -> anyhow::Error;
+
+    /// Check if the expression is acceptable for the dependency.
+    ///
+    /// Returns the minimal set of licenses satisfying the check.
+    fn check(&self, dependency: &Dependency, expression: &Expression) -> Result<Vec<LicenseReq>> {
+        minimal_licenses(expression, |r| self.accepts(dependency, r))
+            .ok_or_else(|| self.reject(expression))
+    }
+}
+
+/// Compute the minimal set of accepted licenses, satisfying the expression.
+///
+/// For `OR` expressions, the branch requiring fewer licenses is chosen, preferring the left one.
+/// Returns `None` if the expression cannot be satisfied with the accepted licenses.
+pub fn minimal_licenses<F>(expression: &Expression, accepts: F) -> Option<Vec<LicenseReq>>
+where
+    F: Fn(&LicenseReq) -> bool,
+{
+    let mut stack: Vec<Option<Vec<LicenseReq>>> = Vec::new();
+
+    for node in expression.iter() {
+        match node {
+            ExprNode::Req(r) => stack.push(accepts(&r.req).then(|| vec![r.req.clone()])),
+            ExprNode::Op(op) => {
+                let rhs = stack.pop()?;
+                let lhs = stack.pop()?;
+                stack.push(match (op, lhs, rhs) {
+                    (Operator::And, Some(mut lhs), Some(rhs)) => {
+                        for r in rhs {
+                            if !lhs.contains(&r) {
+                                lhs.push(r);
+                            }
+                        }
+                        Some(lhs)
+                    }
+                    (Operator::And, _, _) => None,
+                    (Operator::Or, Some(lhs), Some(rhs)) if rhs.len() < lhs.len() => Some(rhs),
+                    (Operator::Or, Some(lhs), _) => Some(lhs),
+                    (Operator::Or, None, rhs) => rhs,
+                });
+            }
+        }
+    }
+
+    stack.pop()?
+}
+
+/// Check if two expressions are logically equivalent.
+///
+/// This evaluates both expressions for all combinations of their licenses, so `MIT OR Apache-2.0`
+/// is equivalent to `Apache-2.0 OR MIT`. For expressions with too many licenses, only the sets of
+/// licenses are compared.
+pub fn equivalent(a: &Expression, b: &Expression) -> bool {
+    let mut licenses: Vec<&LicenseReq> = a.requirements().map(|r| &r.req).collect();
+    for r in b.requirements() {
+        if !licenses.contains(&&r.req) {
+            licenses.push(&r.req);
+        }
+    }
+
+    if licenses.len() > 12 {
+        let mut a: Vec<_> = a.requirements().map(|r| &r.req).collect();
+        let mut b: Vec<_> = b.requirements().map(|r| &r.req).collect();
+        a.sort();
+        a.dedup();
+        b.sort();
+        b.dedup();
+        return a == b;
+    }
+
+    (0..1u32 << licenses.len()).all(|bits| {
+        let accepted = |r: &LicenseReq| {
+            licenses
+                .iter()
+                .position(|l| *l == r)
+                .map(|i| bits & (1 << i) != 0)
+                .unwrap_or_default()
+        };
+        a.evaluate(accepted) == b.evaluate(accepted)
+    })
+}
+
+/// Check if two declared licenses differ.
+///
+/// Both licenses are compared as expressions, so reordering an expression isn't a change. If
+/// either of them can't be parsed, the raw strings are compared.
+pub fn license_differs(a: Option<&str>, b: Option<&str>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => match (
+            Expression::parse_mode(a, ParseMode::LAX),
+            Expression::parse_mode(b, ParseMode::LAX),
+        ) {
+            (Ok(a), Ok(b)) => !equivalent(&a, &b),
+            _ => a != b,
+        },
+        (a, b) => a != b,
+    }
+}
+
+/// Format a set of licenses, as a conjunction.
+pub fn format_licenses(licenses: &[LicenseReq]) -> String {
+    licenses
+        .iter()
+        .map(|l| l.to_string())
+        .collect::<Vec<_>>()
+        .join(" AND ")
 }
 
 /// Check if the dependency has an OSI approved license
 pub struct OsiApproved;
 
 impl LicenseCheck for OsiApproved {
-    fn check(&self, expression: &Expression) -> Result<()> {
-        let r = expression.evaluate(|r| match r.license {
+    fn accepts(&self, _dependency: &Dependency, license: &LicenseReq) -> bool {
+        match license.license {
             LicenseItem::Spdx { id, .. } => id.is_osi_approved(),
             _ => false,
-        });
-
-        match r {
-            true => Ok(()),
-            false => Err(anyhow!("{} is not OSI approved", expression)),
         }
     }
+
+    fn reject(&self, expression: &Expression) -> anyhow::Error {
+        anyhow!("{} is not OSI approved", expression)
+    }
 }
 
 /// Check if the dependency has any of the approved licenses
@@ -145,36 +363,189 @@ pub struct ApprovedLicenses {
 }
 
 impl LicenseCheck for ApprovedLicenses {
-    fn check(&self, expression: &Expression) -> Result<()> {
-        let r = expression.evaluate(|r| match r.license {
+    fn accepts(&self, _dependency: &Dependency, license: &LicenseReq) -> bool {
+        match license.license {
             LicenseItem::Spdx { id, .. } => self.licenses.contains(&id),
             _ => false,
-        });
-
-        match r {
-            true => Ok(()),
-            false => Err(anyhow!("{} is not OSI approved", expression)),
         }
     }
+
+    fn reject(&self, expression: &Expression) -> anyhow::Error {
+        anyhow!("{} is not an approved license", expression)
+    }
+}
+
+/// Check if the dependency has a license of the allowed categories
+pub struct AllowedCategories {
+    pub categories: Categories,
+    pub allowed: Vec<CategoryRule>,
+}
+
+impl LicenseCheck for AllowedCategories {
+    fn accepts(&self, dependency: &Dependency, license: &LicenseReq) -> bool {
+        self.allowed.iter().any(|rule| {
+            rule.applies(dependency) && self.categories.contains(&rule.category, license)
+        })
+    }
+
+    fn reject(&self, expression: &Expression) -> anyhow::Error {
+        anyhow!("{} is not in an allowed license category", expression)
+    }
+}
+
+/// Check if the dependency can be used without a license of the denied categories
+pub struct DeniedCategories {
+    pub categories: Categories,
+    pub denied: Vec<CategoryRule>,
+}
+
+impl LicenseCheck for DeniedCategories {
+    fn accepts(&self, dependency: &Dependency, license: &LicenseReq) -> bool {
+        !self.denied.iter().any(|rule| {
+            rule.applies(dependency) && self.categories.contains(&rule.category, license)
+        })
+    }
+
+    fn reject(&self, expression: &Expression) -> anyhow::Error {
+        anyhow!("{} requires a denied license category", expression)
+    }
 }
 
 impl Dependency {
+    pub fn new(name: String, version: Version) -> Self {
+        Dependency {
+            name,
+            version,
+            clearly_defined: None,
+            passed_license: Outcome::Ignore,
+            passed_score: Vec::new(),
+            effective_license: None,
+            reasons: Vec::new(),
+            previous: None,
+            passed_license_change: Outcome::Ignore,
+            metadata: None,
+            passed_metadata: Outcome::Ignore,
+            curations: Vec::new(),
+        }
+    }
+
+    /// The raw declared license, if present.
+    pub fn declared_license(&self) -> Option<&str> {
+        self.clearly_defined
+            .as_ref()
+            .and_then(|cd| cd.declared_license.as_ref())
+            .map(|l| l.raw.as_str())
+    }
+
+    /// Add a reason, produced by the check.
+    pub fn add_reason(&mut self, check: Check, message: impl Into<String>) {
+        self.reasons.push(Reason {
+            check,
+            message: message.into(),
+        });
+    }
+
+    /// The messages of all reasons.
+    pub fn reason_messages(&self) -> Vec<&str> {
+        self.reasons.iter().map(|r| r.message.as_str()).collect()
+    }
+
+    /// The messages of the reasons, produced by the check.
+    pub fn reasons_of(&self, check: Check) -> Vec<&str> {
+        self.reasons
+            .iter()
+            .filter(|r| r.check == check)
+            .map(|r| r.message.as_str())
+            .collect()
+    }
+
+    /// Check if the declared license changed, compared to the previous version.
+    ///
+    /// Returns `None` if the previous version is not known.
+    pub fn license_changed(&self) -> Option<bool> {
+        self.previous
+            .as_ref()
+            .map(|prev| license_differs(prev.declared_license.as_deref(), self.declared_license()))
+    }
+
+    /// Turn all warnings into failures.
+    pub fn deny_warnings(&mut self) {
+        self.passed_license = self.passed_license.deny_warning();
+        self.passed_license_change = self.passed_license_change.deny_warning();
+        self.passed_metadata = self.passed_metadata.deny_warning();
+        for outcome in &mut self.passed_score {
+            *outcome = outcome.deny_warning();
+        }
+    }
+
     /// Check if the dependency passed all tests.
     pub fn passed(&self) -> bool {
+        let score = self.passed_score.iter().all(|outcome| !outcome.is_fail());
+
         #[allow(clippy::match_like_matches_macro)]
-        match (self.passed_score, self.passed_license) {
-            (Outcome::Fail, _) => false,
-            (_, Outcome::Fail) => false,
+        match (
+            score,
+            self.passed_license,
+            self.passed_license_change,
+            self.passed_metadata,
+        ) {
+            (false, _, _, _) => false,
+            (_, Outcome::Fail, _, _) => false,
+            (_, _, Outcome::Fail, _) => false,
+            (_, _, _, Outcome::Fail) => false,
             _ => true,
         }
     }
 
+    /// Check that the declared license agrees with the license of the crate metadata.
+    pub fn test_metadata(&self, lax: bool) -> Result<()> {
+        let metadata = match &self.metadata {
+            Some(metadata) => metadata,
+            None => return Err(anyhow!("Missing crate metadata")),
+        };
+
+        let license = match (&metadata.license, metadata.license_file) {
+            (Some(license), _) => license,
+            (None, true) => {
+                return Err(anyhow!(
+                    "Crate uses a license file, which requires a manual review"
+                ))
+            }
+            (None, false) => return Err(anyhow!("Crate has no license information")),
+        };
+
+        let crate_expression = License::new(license.clone())?
+            .expression(lax)
+            .map_err(|e| anyhow!("Crate license {} is invalid: {}", license, e))?;
+
+        let declared_expression = match &self.clearly_defined {
+            Some(ClearlyDefined {
+                declared_license: Some(declared),
+                ..
+            }) => declared.expression(lax)?,
+            _ => return Err(anyhow!("Missing license information")),
+        };
+
+        match equivalent(&crate_expression, &declared_expression) {
+            true => Ok(()),
+            false => Err(anyhow!(
+                "Declared license {} doesn't agree with the crate license {}",
+                declared_expression,
+                crate_expression
+            )),
+        }
+    }
+
     /// run the license test.
+    ///
+    /// On success, returns the outcome and the minimal set of licenses satisfying all checks. If
+    /// the checks are only satisfied by choosing some licenses of an `OR` expression, the outcome
+    /// is a warning.
     pub fn test_license(
         &self,
         lax: bool,
         checks: &[Box<dyn LicenseCheck>],
-    ) -> Result<(), Vec<anyhow::Error>> {
+    ) -> Result<(Outcome, Vec<LicenseReq>), Vec<anyhow::Error>> {
         let license = match &self.clearly_defined {
             Some(ClearlyDefined {
                 declared_license: Some(license),
@@ -187,16 +558,112 @@ impl Dependency {
 
         let errors: Vec<_> = checks
             .iter()
-            .map(|check| check.check(&expression))
+            .map(|check| check.check(self, &expression))
             .flat_map(|r| match r {
                 Ok(_) => None,
                 Err(e) => e.into(),
             })
             .collect();
 
-        match errors.len() {
-            0 => Ok(()),
-            _ => Err(errors),
+        if !errors.is_empty() {
+            return Err(errors);
         }
+
+        let accepts = |r: &LicenseReq| checks.iter().all(|check| check.accepts(self, r));
+
+        let licenses = minimal_licenses(&expression, accepts).ok_or_else(|| {
+            vec![anyhow!(
+                "{} has no choice of licenses satisfying all checks",
+                expression
+            )]
+        })?;
+
+        let outcome = match expression.requirements().all(|r| accepts(&r.req)) {
+            true => Outcome::Pass,
+            false => Outcome::Warn,
+        };
+
+        Ok((outcome, licenses))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn minimal_license_set() {
+        let minimal = |expression: &str, accepted: &[&str]| {
+            let expression = Expression::parse(expression).unwrap();
+            minimal_licenses(&expression, |r| accepted.contains(&r.to_string().as_str()))
+                .map(|l| format_licenses(&l))
+        };
+
+        assert_eq!(
+            minimal("MIT OR Apache-2.0", &["MIT", "Apache-2.0"]).as_deref(),
+            Some("MIT")
+        );
+        assert_eq!(
+            minimal("MIT OR Apache-2.0", &["Apache-2.0"]).as_deref(),
+            Some("Apache-2.0")
+        );
+        assert_eq!(
+            minimal(
+                "(MIT AND BSD-3-Clause) OR Apache-2.0",
+                &["MIT", "BSD-3-Clause", "Apache-2.0"]
+            )
+            .as_deref(),
+            Some("Apache-2.0")
+        );
+        assert_eq!(
+            minimal("Apache-2.0 AND (MIT OR GPL-3.0)", &["MIT", "Apache-2.0"]).as_deref(),
+            Some("Apache-2.0 AND MIT")
+        );
+        assert_eq!(minimal("MIT AND GPL-3.0", &["MIT"]), None);
+    }
+
+    #[test]
+    fn equivalent_expressions() {
+        let equivalent = |a: &str, b: &str| {
+            super::equivalent(
+                &Expression::parse_mode(a, ParseMode::LAX).unwrap(),
+                &Expression::parse_mode(b, ParseMode::LAX).unwrap(),
+            )
+        };
+
+        assert!(equivalent("MIT OR Apache-2.0", "Apache-2.0 OR MIT"));
+        assert!(equivalent("MIT/Apache-2.0", "MIT OR Apache-2.0"));
+        assert!(!equivalent("MIT OR Apache-2.0", "MIT AND Apache-2.0"));
+        assert!(!equivalent("MIT", "MIT OR Apache-2.0"));
+    }
+
+    #[test]
+    fn changed_license() {
+        let changed = |previous: Option<&str>, declared: Option<&str>| {
+            let mut dep = Dependency::new("a".into(), Version::new(1, 1, 0));
+            dep.clearly_defined = Some(ClearlyDefined::new(
+                declared.map(|l| License::new(l.into()).unwrap()),
+                0,
+                0,
+                Vec::new(),
+            ));
+            dep.previous = Some(PreviousVersion {
+                version: Version::new(1, 0, 0),
+                declared_license: previous.map(Into::into),
+            });
+            dep.license_changed()
+        };
+
+        assert_eq!(
+            changed(Some("Apache-2.0 OR MIT"), Some("MIT OR Apache-2.0")),
+            Some(false)
+        );
+        assert_eq!(changed(Some("MIT"), Some("MIT AND Apache-2.0")), Some(true));
+        assert_eq!(changed(Some("MIT"), None), Some(true));
+        assert_eq!(
+            changed(Some("not a license"), Some("not a license")),
+            Some(false)
+        );
+        assert_eq!(changed(Some("not a license"), Some("MIT")), Some(true));
     }
 }
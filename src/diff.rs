@@ -0,0 +1,301 @@
+/*
+ * Copyright (c) 2020 Red Hat Inc.
+ *
+ * See the NOTICE file(s) distributed with this work for additional
+ * information regarding copyright ownership.
+ *
+ * This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License 2.0 which is available at
+ * http://www.eclipse.org/legal/epl-2.0
+ *
+ * SPDX-License-Identifier: EPL-2.0
+ */
+
+use crate::args::{Args, DiffArgs};
+use crate::config::Config;
+use crate::data::{license_differs, Dependency};
+use crate::lockfile::LockfileSource;
+use crate::policy::ScoreType;
+use crate::{base_dir, cd, definition_source, report};
+use anyhow::{anyhow, Context, Result};
+use cargo_lock::Lockfile;
+use semver::Version;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::process::ExitCode;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Change {
+    Added,
+    Removed,
+    Upgraded,
+    Downgraded,
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::Added => f.write_str("added"),
+            Change::Removed => f.write_str("removed"),
+            Change::Upgraded => f.write_str("upgraded"),
+            Change::Downgraded => f.write_str("downgraded"),
+        }
+    }
+}
+
+/// A changed crate, between two lockfiles.
+#[derive(Debug, Clone)]
+pub struct DiffEntry {
+    pub name: String,
+    pub old: Option<Dependency>,
+    pub new: Option<Dependency>,
+}
+
+impl DiffEntry {
+    pub fn change(&self) -> Change {
+        match (&self.old, &self.new) {
+            (Some(old), Some(new)) if new.version < old.version => Change::Downgraded,
+            (Some(_), Some(_)) => Change::Upgraded,
+            (None, _) => Change::Added,
+            (_, None) => Change::Removed,
+        }
+    }
+
+    /// Check if the declared license changed between the old and the new version.
+    pub fn license_changed(&self) -> bool {
+        match (&self.old, &self.new) {
+            (Some(old), Some(new)) => {
+                license_differs(old.declared_license(), new.declared_license())
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Collect the versions of each crate, from pairs of name and version.
+pub fn collect_versions<'a>(
+    packages: impl IntoIterator<Item = (&'a str, &'a Version)>,
+) -> BTreeMap<String, Vec<Version>> {
+    let mut result = BTreeMap::<_, Vec<_>>::new();
+    for (name, version) in packages {
+        result
+            .entry(name.to_string())
+            .or_default()
+            .push(version.clone());
+    }
+    result
+}
+
+/// Collect the locked versions of each crate.
+pub fn versions(lockfile: &Lockfile) -> BTreeMap<String, Vec<Version>> {
+    collect_versions(
+        lockfile
+            .packages
+            .iter()
+            .map(|p| (p.name.as_str(), &p.version)),
+    )
+}
+
+/// Compute the changed crates between two lockfiles.
+///
+/// If a crate has multiple versions, the removed and added versions are paired in order, so that
+/// they are reported as upgrades. Remaining versions are reported as added or removed.
+pub fn diff(old: &Lockfile, new: &Lockfile) -> Vec<DiffEntry> {
+    let old = versions(old);
+    let mut new = versions(new);
+
+    let mut result = Vec::new();
+
+    for (name, old_versions) in old {
+        let new_versions = new.remove(&name).unwrap_or_default();
+
+        let mut removed: Vec<_> = old_versions
+            .iter()
+            .filter(|v| !new_versions.contains(v))
+            .cloned()
+            .collect();
+        let mut added: Vec<_> = new_versions
+            .into_iter()
+            .filter(|v| !old_versions.contains(v))
+            .collect();
+        removed.sort();
+        added.sort();
+
+        let mut removed = removed.into_iter();
+        let mut added = added.into_iter();
+
+        loop {
+            let entry = DiffEntry {
+                name: name.clone(),
+                old: removed.next().map(|v| Dependency::new(name.clone(), v)),
+                new: added.next().map(|v| Dependency::new(name.clone(), v)),
+            };
+            if entry.old.is_none() && entry.new.is_none() {
+                break;
+            }
+            result.push(entry);
+        }
+    }
+
+    for (name, versions) in new {
+        for version in versions {
+            result.push(DiffEntry {
+                name: name.clone(),
+                old: None,
+                new: Some(Dependency::new(name.clone(), version)),
+            });
+        }
+    }
+
+    result.sort_by(|a, b| a.name.cmp(&b.name));
+
+    result
+}
+
+pub async fn run(args: &Args, diff_args: &DiffArgs) -> Result<ExitCode> {
+    // fail before fetching any definitions
+    for format in std::iter::once(args.output_format).chain(args.outputs.iter().map(|o| o.format)) {
+        if !report::supports_diff(format) {
+            return Err(anyhow!(
+                "The {:?} output format doesn't support comparing lockfiles",
+                format
+            ));
+        }
+    }
+
+    let base = base_dir()?;
+    let old = diff_args.old.load(&base)?;
+    let new = diff_args
+        .new
+        .clone()
+        .unwrap_or_else(|| LockfileSource::File(args.input.clone()))
+        .load(&base)?;
+
+    let mut entries: Vec<_> = diff(&old, &new)
+        .into_iter()
+        .filter(|entry| {
+            !args.exclude.iter().any(|s| {
+                entry
+                    .new
+                    .as_ref()
+                    .or(entry.old.as_ref())
+                    .map(|dep| s.matches(&dep.name, &dep.version))
+                    .unwrap_or_default()
+            })
+        })
+        .collect();
+
+    log::info!("Found {} changed dependencies", entries.len());
+
+    let config = Config::from_args(args)?;
+    let client = config.http.client()?;
+    let source = definition_source(args, &config, &client)?;
+    for entry in &mut entries {
+        if let Some(old) = entry.old.take() {
+            entry.old = Some(cd::lookup_clearlydefined(source.as_ref(), old).await?);
+        }
+        if let Some(new) = entry.new.take() {
+            entry.new = Some(cd::lookup_clearlydefined(source.as_ref(), new).await?);
+        }
+    }
+
+    let mut score_types = Vec::<ScoreType>::new();
+    for req in args.score_requirements() {
+        if !score_types.contains(&req.score_type) {
+            score_types.push(req.score_type);
+        }
+    }
+
+    if !args.quiet {
+        report::show_diff(
+            &mut io::stdout(),
+            args.output_format,
+            &score_types,
+            &entries,
+        )?;
+    }
+
+    for output in &args.outputs {
+        let mut file = BufWriter::new(
+            File::create(&output.path)
+                .with_context(|| format!("Failed to create report: {}", output.path.display()))?,
+        );
+        report::show_diff(&mut file, output.format, &score_types, &entries)?;
+        file.flush()?;
+        log::info!("Written report: {}", output.path.display());
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::OutputFormat;
+    use std::str::FromStr;
+
+    fn lockfile(packages: &[(&str, &str)]) -> Lockfile {
+        let mut s = String::from("version = 3\n");
+        for (name, version) in packages {
+            s.push_str(&format!(
+                "\n[[package]]\nname = \"{}\"\nversion = \"{}\"\n",
+                name, version
+            ));
+        }
+        Lockfile::from_str(&s).unwrap()
+    }
+
+    #[test]
+    fn diff_lockfiles() {
+        let old = lockfile(&[
+            ("a", "1.0.0"),
+            ("b", "1.0.0"),
+            ("c", "0.1.0"),
+            ("c", "0.2.0"),
+        ]);
+        let new = lockfile(&[("a", "1.1.0"), ("c", "0.2.0"), ("d", "1.0.0")]);
+
+        let result: Vec<_> = diff(&old, &new)
+            .iter()
+            .map(|e| (e.name.clone(), e.change()))
+            .collect();
+
+        assert_eq!(
+            result,
+            vec![
+                ("a".to_string(), Change::Upgraded),
+                ("b".to_string(), Change::Removed),
+                ("c".to_string(), Change::Removed),
+                ("d".to_string(), Change::Added),
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_report() {
+        let old = lockfile(&[("a", "1.0.0")]);
+        let new = lockfile(&[("a", "1.1.0"), ("b", "1.0.0")]);
+        let entries = diff(&old, &new);
+
+        let mut out = Vec::new();
+        report::show_diff(
+            &mut out,
+            OutputFormat::Json,
+            &[ScoreType::Effective],
+            &entries,
+        )
+        .unwrap();
+        let report: serde_json::Value = serde_json::from_slice(&out).unwrap();
+
+        assert_eq!(report["changes"][0]["change"], "upgraded");
+        assert_eq!(report["changes"][0]["old"]["version"], "1.0.0");
+        assert_eq!(report["changes"][1]["name"], "b");
+        assert_eq!(report["changes"][1]["old"], serde_json::Value::Null);
+
+        assert!(report::show_diff(&mut out, OutputFormat::Sarif, &[], &entries).is_err());
+    }
+}
@@ -0,0 +1,81 @@
+/*
+ * Copyright (c) 2020 Red Hat Inc.
+ *
+ * See the NOTICE file(s) distributed with this work for additional
+ * information regarding copyright ownership.
+ *
+ * This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License 2.0 which is available at
+ * http://www.eclipse.org/legal/epl-2.0
+ *
+ * SPDX-License-Identifier: EPL-2.0
+ */
+
+//! Download the definitions of a lockfile into a bundle, for checking without network access.
+
+use crate::args::{Args, FetchArgs};
+use crate::baseline::Baseline;
+use crate::config::Config;
+use crate::lockfile::LockfileSource;
+use crate::source::BundleSource;
+use crate::{base_dir, definition_source, diff};
+use anyhow::Result;
+use semver::Version;
+use std::collections::BTreeSet;
+use std::process::ExitCode;
+
+pub async fn run(args: &Args, fetch_args: &FetchArgs) -> Result<ExitCode> {
+    let base = base_dir()?;
+    let lockfile = LockfileSource::File(args.input.clone()).load(&base)?;
+
+    let mut packages: BTreeSet<(String, Version)> = lockfile
+        .packages
+        .iter()
+        .filter(|p| {
+            !args
+                .exclude
+                .iter()
+                .any(|s| s.matches(p.name.as_str(), &p.version))
+        })
+        .map(|p| (p.name.to_string(), p.version.clone()))
+        .collect();
+
+    // the previous versions are required to detect license changes
+
+    let previous = match (&args.previous, &args.baseline) {
+        (Some(previous), _) => Some(diff::versions(&previous.load(&base)?)),
+        (None, Some(baseline)) => Some(Baseline::load(baseline)?.versions()),
+        _ => None,
+    };
+
+    if let Some(previous) = previous {
+        let names: BTreeSet<_> = packages.iter().map(|(name, _)| name.clone()).collect();
+        for (name, versions) in previous {
+            if names.contains(&name) {
+                packages.extend(versions.into_iter().map(|v| (name.clone(), v)));
+            }
+        }
+    }
+
+    let config = Config::from_args(args)?;
+    let client = config.http.client()?;
+    let source = definition_source(args, &config, &client)?;
+
+    let mut bundle = BundleSource::default();
+    for (name, version) in &packages {
+        match source.definition(name, version).await? {
+            Some(definition) => bundle.insert(name, version, definition),
+            None => log::warn!("No definition found: {}/{}", name, version),
+        }
+        log::info!("Fetched: {}/{}", name, version);
+    }
+
+    bundle.store(&fetch_args.output)?;
+    log::info!(
+        "Written {} definitions to: {}",
+        bundle.len(),
+        fetch_args.output.display()
+    );
+
+    Ok(ExitCode::SUCCESS)
+}
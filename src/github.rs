@@ -0,0 +1,144 @@
+/*
+ * Copyright (c) 2020 Red Hat Inc.
+ *
+ * See the NOTICE file(s) distributed with this work for additional
+ * information regarding copyright ownership.
+ *
+ * This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License 2.0 which is available at
+ * http://www.eclipse.org/legal/epl-2.0
+ *
+ * SPDX-License-Identifier: EPL-2.0
+ */
+
+//! Integration with GitHub Actions: workflow annotations and the job summary.
+
+use crate::data::{Dependency, Outcome};
+use crate::lockfile::package_lines;
+use crate::report::{self, overall, Checks, Options, OutputFormat, Section, Summary};
+use anyhow::{Context, Result};
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Check if we are running in GitHub Actions.
+pub fn detected() -> bool {
+    env::var("GITHUB_ACTIONS")
+        .map(|v| v == "true")
+        .unwrap_or_default()
+}
+
+fn escape_data(input: &str) -> String {
+    input
+        .replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+fn escape_property(input: &str) -> String {
+    escape_data(input).replace(':', "%3A").replace(',', "%2C")
+}
+
+/// Check if annotations can be written to stdout, next to the report of the console.
+///
+/// Workflow commands are read from stdout, appending them to a machine readable report would
+/// corrupt it.
+pub fn annotations_allowed(format: OutputFormat, quiet: bool) -> bool {
+    quiet || matches!(format, OutputFormat::Text | OutputFormat::Markdown)
+}
+
+/// Emit an annotation for each failing dependency (and each dependency with warnings), pointing at
+/// its entry of the lockfile.
+pub fn annotate(out: &mut dyn Write, lockfile: &Path, dependencies: &[Dependency]) -> Result<()> {
+    let content = fs::read_to_string(lockfile)
+        .with_context(|| format!("Failed to read lockfile: {}", lockfile.display()))?;
+    let lines = package_lines(&content);
+
+    // annotations must be relative to the repository
+    let file = env::var_os("GITHUB_WORKSPACE")
+        .map(PathBuf::from)
+        .or_else(|| env::current_dir().ok())
+        .and_then(|dir| lockfile.strip_prefix(dir).ok())
+        .unwrap_or(lockfile);
+
+    for dep in dependencies {
+        let level = match overall(dep) {
+            Outcome::Fail => "error",
+            Outcome::Warn => "warning",
+            Outcome::Pass | Outcome::Override | Outcome::Ignore => continue,
+        };
+
+        let mut properties = format!("file={}", escape_property(&file.display().to_string()));
+        if let Some(line) = lines.get(&(dep.name.clone(), dep.version.to_string())) {
+            properties.push_str(&format!(",line={}", line));
+        }
+        properties.push_str(&format!(
+            ",title={}",
+            escape_property(&format!("{} {}", dep.name, dep.version))
+        ));
+
+        let message = match dep.reasons.is_empty() {
+            true => format!("{} {} failed the checks", dep.name, dep.version),
+            false => dep.reason_messages().join("\n"),
+        };
+
+        writeln!(out, "::{} {}::{}", level, properties, escape_data(&message))?;
+    }
+
+    Ok(())
+}
+
+/// Append the Markdown report to the job summary, if the step provides one.
+pub fn write_summary(
+    options: &Options,
+    checks: &Checks,
+    sections: &[Section],
+    summary: &Summary,
+) -> Result<()> {
+    let path = match env::var_os("GITHUB_STEP_SUMMARY") {
+        Some(path) => path,
+        None => return Ok(()),
+    };
+
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .context("Failed to open the job summary")?;
+    let mut out = BufWriter::new(file);
+
+    writeln!(out, "## ClearlyDefined report")?;
+    writeln!(out)?;
+    report::show(
+        &mut out,
+        OutputFormat::Markdown,
+        options,
+        checks,
+        sections,
+        summary,
+    )?;
+    writeln!(out)?;
+    out.flush()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_annotation() {
+        assert_eq!(escape_data("100%\nok"), "100%25%0Aok");
+        assert_eq!(escape_property("a:b,c"), "a%3Ab%2Cc");
+    }
+
+    #[test]
+    fn annotations_only_for_console_reports() {
+        assert!(annotations_allowed(OutputFormat::Text, false));
+        assert!(annotations_allowed(OutputFormat::Markdown, false));
+        assert!(!annotations_allowed(OutputFormat::Json, false));
+        assert!(annotations_allowed(OutputFormat::Json, true));
+    }
+}
@@ -0,0 +1,408 @@
+/*
+ * Copyright (c) 2020 Red Hat Inc.
+ *
+ * See the NOTICE file(s) distributed with this work for additional
+ * information regarding copyright ownership.
+ *
+ * This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License 2.0 which is available at
+ * http://www.eclipse.org/legal/epl-2.0
+ *
+ * SPDX-License-Identifier: EPL-2.0
+ */
+
+//! Check the dependencies of a `Cargo.lock` file with the data of
+//! [clearlydefined.io](https://clearlydefined.io).
+//!
+//! This is the library behind `cargo clearlydefined`. Next to running the tool ([`run`]), it
+//! provides the ClearlyDefined client ([`cd`]), the dependency model and license checks
+//! ([`data`]), the evaluation of the policy ([`policy`]), and the report rendering ([`report`]).
+
+pub mod args;
+pub mod baseline;
+pub mod category;
+pub mod cd;
+pub mod config;
+pub mod curation;
+pub mod data;
+pub mod diff;
+pub mod fetch;
+mod github;
+pub mod lockfile;
+pub mod metadata;
+pub mod pattern;
+pub mod policy;
+pub mod report;
+pub mod source;
+
+use crate::{
+    args::{Args, Command},
+    baseline::Baseline,
+    category::Categories,
+    config::Config,
+    curation::CuratedSource,
+    data::{
+        AllowedCategories, ApprovedLicenses, Check, DeniedCategories, Dependency, LicenseCheck,
+        OsiApproved,
+    },
+    lockfile::LockfileSource,
+    metadata::MetadataSource,
+    pattern::Selector,
+    policy::Policy,
+    report::{Checks, Section, Summary},
+    source::{BundleSource, CacheSource, ChainSource, DefinitionSource, FixtureSource},
+};
+use anyhow::{anyhow, Context, Result};
+use cargo_lock::Lockfile;
+use std::env;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+fn default_dir() -> Option<PathBuf> {
+    env::var_os("CARGO_MANIFEST_DIR").map(|s| PathBuf::from(&s))
+}
+
+/// The directory to resolve relative input files against.
+fn base_dir() -> Result<PathBuf> {
+    match default_dir() {
+        Some(dir) => Ok(dir),
+        None => Ok(env::current_dir()?),
+    }
+}
+
+/// The source of the definitions, as configured by the arguments.
+fn definition_source(
+    args: &Args,
+    config: &Config,
+    client: &reqwest::Client,
+) -> Result<Box<dyn DefinitionSource>> {
+    let mut source: Box<dyn DefinitionSource> = match &args.definitions {
+        Some(bundle) => Box::new(BundleSource::load(bundle)?),
+        None => Box::new(config.api.source(client.clone())?),
+    };
+
+    if let Some(cache) = &args.cache {
+        source = Box::new(CacheSource::new(cache, source));
+    }
+
+    if let Some(fixtures) = &args.fixtures {
+        source = Box::new(ChainSource::new(vec![
+            Box::new(FixtureSource::new(fixtures)),
+            source,
+        ]));
+    }
+
+    if !args.curations.is_empty() {
+        let mut curations = Vec::new();
+        for path in &args.curations {
+            curations.extend(CuratedSource::load(path)?);
+        }
+        source = Box::new(CuratedSource::new(curations, source));
+    }
+
+    Ok(source)
+}
+
+/// Warn about selectors which don't match any dependency, as they are most likely a mistake.
+fn warn_unmatched<'a>(
+    lockfile: &Lockfile,
+    kind: &str,
+    selectors: impl IntoIterator<Item = &'a Selector>,
+) {
+    for selector in selectors {
+        if !lockfile
+            .packages
+            .iter()
+            .any(|p| selector.matches(p.name.as_str(), &p.version))
+        {
+            log::warn!(
+                "{} pattern '{}' didn't match any dependency",
+                kind,
+                selector
+            );
+        }
+    }
+}
+
+/// The policy, as configured by the arguments.
+fn policy(args: &Args) -> Result<Policy> {
+    let mut license_checks: Vec<Box<dyn LicenseCheck>> = Vec::new();
+    if args.approve_osi {
+        license_checks.push(Box::new(OsiApproved));
+    }
+    if !args.approved_licenses.is_empty() {
+        license_checks.push(Box::new(ApprovedLicenses {
+            licenses: args.approved_licenses.iter().map(|name| name.0).collect(),
+        }))
+    }
+    if !args.allowed_categories.is_empty() || !args.denied_categories.is_empty() {
+        let categories = Categories::load(args.license_categories.as_deref())?;
+        for rule in args
+            .allowed_categories
+            .iter()
+            .chain(&args.denied_categories)
+        {
+            if !categories.exists(&rule.category) {
+                return Err(anyhow!(
+                    "Unknown license category: {} (known: {})",
+                    rule.category,
+                    categories.names().collect::<Vec<_>>().join(", ")
+                ));
+            }
+        }
+
+        if !args.allowed_categories.is_empty() {
+            license_checks.push(Box::new(AllowedCategories {
+                categories: categories.clone(),
+                allowed: args.allowed_categories.clone(),
+            }));
+        }
+        if !args.denied_categories.is_empty() {
+            license_checks.push(Box::new(DeniedCategories {
+                categories,
+                denied: args.denied_categories.clone(),
+            }));
+        }
+    }
+
+    Ok(Policy {
+        score_requirements: args.score_requirements(),
+        score_overrides: args.score_overrides.clone(),
+        ignore: args.ignore.clone(),
+        license_checks,
+        approve_all: args.approve_all,
+        check_metadata: args.check_metadata.is_some(),
+        deny_warnings: args.deny_warnings,
+        lax: args.lax,
+    })
+}
+
+/// Run the command line tool with the parsed arguments.
+pub async fn run(args: &Args) -> Result<ExitCode> {
+    match &args.command {
+        Some(Command::Diff(diff)) => diff::run(args, diff).await,
+        Some(Command::Fetch(fetch)) => fetch::run(args, fetch).await,
+        Some(Command::Curate(curate)) => curation::run(args, curate).await,
+        None => check(args).await,
+    }
+}
+
+async fn check(args: &Args) -> Result<ExitCode> {
+    // fail before doing any work, as the bundle is meant for environments without network access
+    if let (Some(_), Some(MetadataSource::CratesIo)) = (&args.definitions, &args.check_metadata) {
+        return Err(anyhow!(
+            "Checking the metadata with crates.io requires network access, which --definitions avoids. Provide a directory of unpacked crates to --check-metadata instead."
+        ));
+    }
+
+    let base = base_dir()?;
+    let lockfile = LockfileSource::File(args.input.clone()).load(&base)?;
+
+    log::info!("Loaded {} dependencies", lockfile.packages.len());
+
+    warn_unmatched(&lockfile, "Exclude", &args.exclude);
+    warn_unmatched(&lockfile, "Ignore", &args.ignore);
+    warn_unmatched(
+        &lockfile,
+        "Score override",
+        args.score_overrides.iter().map(|o| &o.selector),
+    );
+
+    let exclude = &args.exclude;
+
+    let deps = lockfile
+        .packages
+        .iter()
+        .filter(|&dep| {
+            !exclude
+                .iter()
+                .any(|s| s.matches(dep.name.as_str(), &dep.version))
+        })
+        .map(|p| Dependency::new(p.name.to_string(), p.version.clone()))
+        .collect::<Vec<_>>();
+    let excluded = lockfile.packages.len() - deps.len();
+
+    let baseline = args.baseline.as_deref().map(Baseline::load).transpose()?;
+
+    // the previously locked versions, to detect license changes
+
+    let previous = match (&args.previous, &baseline) {
+        (Some(previous), _) => Some(diff::versions(&previous.load(&base)?)),
+        (None, Some(baseline)) if !baseline.packages.is_empty() => Some(baseline.versions()),
+        _ => None,
+    };
+
+    let config = Config::from_args(args)?;
+    let client = config.http.client()?;
+    let source = definition_source(args, &config, &client)?;
+    let mut deps = cd::lookup_all(source.as_ref(), deps).await?;
+
+    if let Some(previous) = &previous {
+        cd::lookup_previous(source.as_ref(), &mut deps, previous).await?;
+    }
+
+    if let Some(source) = &args.check_metadata {
+        metadata::lookup_all(&client, source, &mut deps).await?;
+    }
+
+    let policy = policy(args)?;
+    policy.evaluate(&mut deps);
+    let score_requirements = &policy.score_requirements;
+
+    if args.pending_curations {
+        let source = config.api.source(client.clone())?;
+        cd::lookup_curations(&source, deps.iter_mut().filter(|dep| !dep.passed())).await?;
+
+        for dep in &mut deps {
+            let reasons: Vec<_> = dep
+                .curations
+                .iter()
+                .map(|curation| {
+                    let state = match curation.merged {
+                        true => "merged, waiting for the definition to be updated",
+                        false => "pending",
+                    };
+                    format!("Curation {}: {} ({})", state, curation.title, curation.url)
+                })
+                .collect();
+            for reason in reasons {
+                dep.add_reason(Check::Curation, reason);
+            }
+        }
+    }
+
+    if let Some(path) = &args.write_baseline {
+        let baseline = Baseline::new(&deps, score_requirements);
+        baseline.store(path)?;
+        log::info!(
+            "Wrote {} failures to baseline: {}",
+            baseline.failures.len(),
+            path.display()
+        );
+    }
+
+    // split off dependencies, which only have known failures
+
+    let mut baselined = Vec::new();
+    if let Some(baseline) = &baseline {
+        for failure in baseline.resolved(&deps, score_requirements) {
+            log::info!(
+                "Baselined failure is resolved: {}/{}: {}",
+                failure.name,
+                failure.version,
+                failure.reason
+            );
+        }
+
+        (baselined, deps) = deps
+            .into_iter()
+            .partition(|dep| baseline.covers(dep, score_requirements));
+    }
+
+    let github_actions = !args.no_github_actions && github::detected();
+
+    if !args.quiet || !args.outputs.is_empty() || github_actions {
+        // now sort it
+        deps.sort();
+        baselined.sort();
+
+        let failed_deps: Vec<_>;
+        let shown = if args.failed {
+            failed_deps = deps.iter().filter(|dep| !dep.passed()).cloned().collect();
+
+            log::info!(
+                "{} dependencies failed the required scores of {}",
+                failed_deps.len(),
+                score_requirements
+                    .iter()
+                    .map(|req| req.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+
+            &failed_deps
+        } else {
+            &deps
+        };
+
+        let mut sections = vec![Section {
+            title: None,
+            dependencies: shown,
+        }];
+        if !baselined.is_empty() {
+            sections.push(Section {
+                title: Some("Baselined failures"),
+                dependencies: &baselined,
+            });
+        }
+
+        let checks = Checks {
+            score_requirements,
+            license: !policy.approve_all,
+            license_checks: &policy.license_checks,
+            license_change: previous.is_some(),
+            metadata: args.check_metadata.is_some(),
+        };
+
+        let options = args.report_options();
+        let summary = Summary::new(&options, &checks, deps.iter().chain(&baselined), excluded);
+
+        if !args.quiet {
+            report::show(
+                &mut io::stdout(),
+                args.output_format,
+                &options,
+                &checks,
+                &sections,
+                &summary,
+            )?;
+        }
+
+        for output in &args.outputs {
+            let mut file =
+                BufWriter::new(File::create(&output.path).with_context(|| {
+                    format!("Failed to create report: {}", output.path.display())
+                })?);
+            report::show(
+                &mut file,
+                output.format,
+                &options,
+                &checks,
+                &sections,
+                &summary,
+            )?;
+            file.flush()?;
+            log::info!("Written report: {}", output.path.display());
+        }
+
+        if github_actions {
+            if github::annotations_allowed(args.output_format, args.quiet) {
+                github::annotate(&mut io::stdout(), &base.join(&args.input), &deps)?;
+            } else {
+                log::warn!(
+                    "Skipping GitHub annotations, as they would corrupt the report on stdout. Use --output to write the report to a file instead."
+                );
+            }
+            github::write_summary(&options, &checks, &sections, &summary)?;
+        }
+    }
+
+    if !args.quiet && policy.license_checks.is_empty() {
+        log::warn!("You have no license checks. Try --approve-osi, --approve-all, --allow-category, or provide a manual selection using e.g. --approve <spdx-license>");
+    }
+
+    let failed = deps.iter().filter(|&d| !d.passed()).count();
+    match failed {
+        0 => Ok(ExitCode::SUCCESS),
+        _ => {
+            log::error!(
+                "{} dependencies out of {} failed at least one of the tests",
+                failed,
+                deps.len() + baselined.len()
+            );
+            Ok(ExitCode::FAILURE)
+        }
+    }
+}
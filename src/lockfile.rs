@@ -0,0 +1,162 @@
+/*
+ * Copyright (c) 2020 Red Hat Inc.
+ *
+ * See the NOTICE file(s) distributed with this work for additional
+ * information regarding copyright ownership.
+ *
+ * This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License 2.0 which is available at
+ * http://www.eclipse.org/legal/epl-2.0
+ *
+ * SPDX-License-Identifier: EPL-2.0
+ */
+
+use anyhow::{anyhow, Context, Result};
+use cargo_lock::Lockfile;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::str::FromStr;
+
+/// The source of a lockfile, either a file, or a file at a git revision.
+#[derive(Debug, Clone)]
+pub enum LockfileSource {
+    /// A lockfile on the filesystem
+    File(PathBuf),
+    /// A lockfile at a git revision, in the format `<rev>:<path>`
+    Git { rev: String, path: PathBuf },
+}
+
+impl FromStr for LockfileSource {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if Path::new(s).exists() {
+            return Ok(LockfileSource::File(s.into()));
+        }
+
+        match s.split_once(':') {
+            Some((rev, path)) if !rev.is_empty() => Ok(LockfileSource::Git {
+                rev: rev.into(),
+                path: match path {
+                    "" => "Cargo.lock".into(),
+                    path => path.into(),
+                },
+            }),
+            _ => Ok(LockfileSource::File(s.into())),
+        }
+    }
+}
+
+impl fmt::Display for LockfileSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LockfileSource::File(path) => write!(f, "{}", path.display()),
+            LockfileSource::Git { rev, path } => write!(f, "{}:{}", rev, path.display()),
+        }
+    }
+}
+
+impl LockfileSource {
+    /// Load the lockfile, resolving relative paths using the base directory.
+    pub fn load(&self, base: &Path) -> Result<Lockfile> {
+        log::info!("Loading from: {}", self);
+
+        match self {
+            LockfileSource::File(path) => Ok(Lockfile::load(base.join(path))?),
+            LockfileSource::Git { rev, path } => {
+                let path = base.join(path);
+                let dir = path.parent().unwrap_or(base);
+                let file = path
+                    .file_name()
+                    .ok_or_else(|| anyhow!("Invalid lockfile path: {}", path.display()))?;
+
+                let output = Command::new("git")
+                    .arg("-C")
+                    .arg(dir)
+                    .arg("show")
+                    .arg(format!("{}:./{}", rev, file.to_string_lossy()))
+                    .output()
+                    .context("Failed to run git")?;
+
+                if !output.status.success() {
+                    return Err(anyhow!(
+                        "Failed to load lockfile from git ({}): {}",
+                        self,
+                        String::from_utf8_lossy(&output.stderr).trim()
+                    ));
+                }
+
+                Ok(Lockfile::from_str(&String::from_utf8(output.stdout)?)?)
+            }
+        }
+    }
+}
+
+/// Find the line (starting with 1) of the `name` of each package of a lockfile, keyed by name and
+/// version.
+pub fn package_lines(content: &str) -> BTreeMap<(String, String), usize> {
+    let mut result = BTreeMap::new();
+    let mut name: Option<(&str, usize)> = None;
+    let mut version: Option<&str> = None;
+
+    let value = |line: &str, key: &str| -> Option<String> {
+        let (k, v) = line.split_once('=')?;
+        match k.trim() == key {
+            true => Some(v.trim().trim_matches('"').to_string()),
+            false => None,
+        }
+    };
+
+    let mut flush = |name: &mut Option<(&str, usize)>, version: &mut Option<&str>| {
+        if let (Some((name, line)), Some(version)) = (name.take(), version.take()) {
+            if let (Some(name), Some(version)) = (value(name, "name"), value(version, "version")) {
+                result.insert((name, version), line);
+            }
+        }
+    };
+
+    for (i, line) in content.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') {
+            flush(&mut name, &mut version);
+        } else if value(trimmed, "name").is_some() {
+            name = Some((trimmed, i + 1));
+        } else if value(trimmed, "version").is_some() {
+            version = Some(trimmed);
+        }
+    }
+    flush(&mut name, &mut version);
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_package_lines() {
+        let content = r#"version = 3
+
+[[package]]
+name = "a"
+version = "1.0.0"
+
+[[package]]
+name = "b"
+version = "0.2.0"
+dependencies = [
+ "a",
+]
+
+[metadata]
+"#;
+
+        let lines = package_lines(content);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[&("a".to_string(), "1.0.0".to_string())], 4);
+        assert_eq!(lines[&("b".to_string(), "0.2.0".to_string())], 8);
+    }
+}
@@ -0,0 +1,161 @@
+/*
+ * Copyright (c) 2020 Red Hat Inc.
+ *
+ * See the NOTICE file(s) distributed with this work for additional
+ * information regarding copyright ownership.
+ *
+ * This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License 2.0 which is available at
+ * http://www.eclipse.org/legal/epl-2.0
+ *
+ * SPDX-License-Identifier: EPL-2.0
+ */
+
+use crate::data::{CrateMetadata, Dependency};
+use anyhow::{Context, Result};
+use reqwest::Client;
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::time::{interval, MissedTickBehavior};
+
+const CRATES_IO: &str = "crates-io";
+/// The crawler policy of crates.io allows at most one request per second.
+const CRATES_IO_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The source of the crate metadata.
+#[derive(Debug, Clone)]
+pub enum MetadataSource {
+    /// The crates.io API
+    CratesIo,
+    /// A directory of unpacked crates, like `~/.cargo/registry/src/<registry>` or the output of
+    /// `cargo vendor`
+    Directory(PathBuf),
+}
+
+impl FromStr for MetadataSource {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            CRATES_IO => Ok(MetadataSource::CratesIo),
+            path => Ok(MetadataSource::Directory(path.into())),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Manifest {
+    package: Package,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct Package {
+    version: String,
+    license: Option<String>,
+    license_file: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct CratesIoResponse {
+    version: CratesIoVersion,
+}
+
+#[derive(Deserialize)]
+struct CratesIoVersion {
+    license: Option<String>,
+}
+
+/// Lookup the crate metadata of all dependencies.
+pub async fn lookup_all(
+    client: &Client,
+    source: &MetadataSource,
+    dependencies: &mut [Dependency],
+) -> Result<()> {
+    let mut throttle = interval(CRATES_IO_INTERVAL);
+    throttle.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    for dep in dependencies {
+        dep.metadata = match source {
+            MetadataSource::CratesIo => {
+                throttle.tick().await;
+                // the metadata is only a cross-check, missing metadata results in a warning
+                match lookup_crates_io(client, dep).await {
+                    Ok(metadata) => metadata,
+                    Err(err) => {
+                        log::warn!(
+                            "Failed to fetch crate metadata of {}/{}: {}",
+                            dep.name,
+                            dep.version,
+                            err
+                        );
+                        None
+                    }
+                }
+            }
+            MetadataSource::Directory(dir) => lookup_directory(dir, dep)?,
+        };
+        log::info!("Processed metadata: {}/{}", dep.name, dep.version);
+    }
+
+    Ok(())
+}
+
+async fn lookup_crates_io(
+    client: &Client,
+    dependency: &Dependency,
+) -> Result<Option<CrateMetadata>> {
+    let url = format!(
+        "https://crates.io/api/v1/crates/{}/{}",
+        dependency.name, dependency.version
+    );
+
+    // crates.io requires a user agent, which is set by the client
+    let response = client.get(&url).send().await?;
+
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+
+    let response: CratesIoResponse = response.error_for_status()?.json().await?;
+
+    // crates.io requires either a license or a license file
+    Ok(Some(CrateMetadata {
+        license_file: response.version.license.is_none(),
+        license: response.version.license,
+    }))
+}
+
+fn lookup_directory(dir: &Path, dependency: &Dependency) -> Result<Option<CrateMetadata>> {
+    let candidates = [
+        dir.join(format!("{}-{}", dependency.name, dependency.version)),
+        dir.join(&dependency.name),
+    ];
+
+    for candidate in candidates {
+        let path = candidate.join("Cargo.toml");
+        if !path.is_file() {
+            continue;
+        }
+
+        let manifest: Manifest = toml::from_str(
+            &fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read manifest: {}", path.display()))?,
+        )
+        .with_context(|| format!("Failed to parse manifest: {}", path.display()))?;
+
+        if manifest.package.version != dependency.version.to_string() {
+            continue;
+        }
+
+        return Ok(Some(CrateMetadata {
+            license: manifest.package.license,
+            license_file: manifest.package.license_file.is_some(),
+        }));
+    }
+
+    Ok(None)
+}
@@ -0,0 +1,132 @@
+/*
+ * Copyright (c) 2020 Red Hat Inc.
+ *
+ * See the NOTICE file(s) distributed with this work for additional
+ * information regarding copyright ownership.
+ *
+ * This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License 2.0 which is available at
+ * http://www.eclipse.org/legal/epl-2.0
+ *
+ * SPDX-License-Identifier: EPL-2.0
+ */
+
+use globset::{GlobBuilder, GlobMatcher};
+use regex::Regex;
+use semver::{Version, VersionReq};
+use std::fmt;
+use std::str::FromStr;
+
+const REGEX_PREFIX: &str = "regex:";
+
+/// A pattern, matching crate names.
+///
+/// Patterns are glob patterns by default (e.g. `winapi-*`), or regular expressions when prefixed
+/// with `regex:` (e.g. `regex:^winapi-.*-gnu$`).
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    Glob(GlobMatcher),
+    Regex(Regex),
+}
+
+impl Pattern {
+    pub fn matches(&self, name: &str) -> bool {
+        match self {
+            Pattern::Glob(glob) => glob.is_match(name),
+            Pattern::Regex(regex) => regex.is_match(name),
+        }
+    }
+}
+
+impl FromStr for Pattern {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.strip_prefix(REGEX_PREFIX) {
+            Some(regex) => Ok(Pattern::Regex(Regex::new(regex)?)),
+            None => Ok(Pattern::Glob(
+                GlobBuilder::new(s)
+                    .literal_separator(false)
+                    .build()?
+                    .compile_matcher(),
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Pattern::Glob(glob) => f.write_str(glob.glob().glob()),
+            Pattern::Regex(regex) => write!(f, "{}{}", REGEX_PREFIX, regex.as_str()),
+        }
+    }
+}
+
+/// A selector for crates, matching a name pattern and an optional version requirement.
+///
+/// The version requirement is separated by an `@` (e.g. `acme-*@<2`).
+#[derive(Debug, Clone)]
+pub struct Selector {
+    pub pattern: Pattern,
+    pub version: Option<VersionReq>,
+}
+
+impl Selector {
+    pub fn matches(&self, name: &str, version: &Version) -> bool {
+        self.pattern.matches(name)
+            && self
+                .version
+                .as_ref()
+                .map(|req| req.matches(version))
+                .unwrap_or(true)
+    }
+}
+
+impl FromStr for Selector {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.rsplit_once('@') {
+            Some((pattern, version)) => Ok(Selector {
+                pattern: pattern.parse()?,
+                version: Some(version.parse()?),
+            }),
+            None => Ok(Selector {
+                pattern: s.parse()?,
+                version: None,
+            }),
+        }
+    }
+}
+
+impl fmt::Display for Selector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.version {
+            Some(version) => write!(f, "{}@{}", self.pattern, version),
+            None => write!(f, "{}", self.pattern),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn match_patterns() {
+        let glob: Pattern = "winapi-*-pc-windows-gnu".parse().unwrap();
+        assert!(glob.matches("winapi-i686-pc-windows-gnu"));
+        assert!(!glob.matches("winapi"));
+
+        let regex: Pattern = "regex:^wasm-bindgen(-.*)?$".parse().unwrap();
+        assert!(regex.matches("wasm-bindgen"));
+        assert!(regex.matches("wasm-bindgen-macro"));
+        assert!(!regex.matches("js-sys"));
+
+        let selector: Selector = "windows_*@>=0.48, <0.52".parse().unwrap();
+        assert!(selector.matches("windows_x86_64_gnu", &Version::new(0, 48, 5)));
+        assert!(!selector.matches("windows_x86_64_gnu", &Version::new(0, 52, 0)));
+        assert!(!selector.matches("windows-sys", &Version::new(0, 48, 0)));
+    }
+}
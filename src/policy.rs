@@ -0,0 +1,354 @@
+/*
+ * Copyright (c) 2020 Red Hat Inc.
+ *
+ * See the NOTICE file(s) distributed with this work for additional
+ * information regarding copyright ownership.
+ *
+ * This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License 2.0 which is available at
+ * http://www.eclipse.org/legal/epl-2.0
+ *
+ * SPDX-License-Identifier: EPL-2.0
+ */
+
+//! The policy the dependencies are checked against, and its evaluation.
+
+use crate::data::{format_licenses, Check, Dependency, LicenseCheck, Outcome};
+use crate::pattern::Selector;
+use anyhow::anyhow;
+use clap::ValueEnum;
+use serde::Serialize;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScoreType {
+    Effective,
+    Licensed,
+}
+
+impl fmt::Display for ScoreType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScoreType::Effective => f.write_str("effective"),
+            ScoreType::Licensed => f.write_str("licensed"),
+        }
+    }
+}
+
+/// A score, of a specific type, which is required to pass the test.
+#[derive(Debug, Clone, Copy)]
+pub struct ScoreRequirement {
+    pub score_type: ScoreType,
+    pub score: u64,
+    /// Warn if the score is below this score, but still passes.
+    pub warn: Option<u64>,
+}
+
+impl ScoreRequirement {
+    /// Test a score, using the score of the override instead of the required one, if present.
+    ///
+    /// Returns the outcome, and the reason for a warning or failure. The outcome is
+    /// [`Outcome::Override`] if the score only passes due to the override.
+    pub fn test(
+        &self,
+        score: u64,
+        score_override: Option<&ScoreOverride>,
+    ) -> (Outcome, Option<String>) {
+        let required = score_override.map(|o| o.score).unwrap_or(self.score);
+        let below = |limit| {
+            format!(
+                "The {} score of {} is below {}",
+                self.score_type, score, limit
+            )
+        };
+
+        match self.warn {
+            _ if score < required => (Outcome::Fail, Some(below(required))),
+            _ if score < self.score => (Outcome::Override, None),
+            Some(warn) if score < warn => (Outcome::Warn, Some(below(warn))),
+            _ => (Outcome::Pass, None),
+        }
+    }
+}
+
+impl fmt::Display for ScoreRequirement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.score, self.score_type)
+    }
+}
+
+/// A score, overriding the required score for all crates matching the selector.
+#[derive(Debug, Clone)]
+pub struct ScoreOverride {
+    pub selector: Selector,
+    pub score: u64,
+}
+
+impl FromStr for ScoreOverride {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.rsplit_once('=') {
+            Some((selector, score)) => Ok(ScoreOverride {
+                selector: selector.parse()?,
+                score: score.trim().parse()?,
+            }),
+            None => Err(anyhow!("Missing score, expected <selector>=<score>: {}", s)),
+        }
+    }
+}
+
+/// A license category, optionally limited to crates matching a selector.
+#[derive(Debug, Clone)]
+pub struct CategoryRule {
+    pub category: String,
+    pub selector: Option<Selector>,
+}
+
+impl CategoryRule {
+    /// Check if the rule applies to the dependency.
+    pub fn applies(&self, dependency: &Dependency) -> bool {
+        self.selector
+            .as_ref()
+            .map(|s| s.matches(&dependency.name, &dependency.version))
+            .unwrap_or(true)
+    }
+}
+
+impl fmt::Display for CategoryRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.selector {
+            Some(selector) => write!(f, "{}:{}", self.category, selector),
+            None => write!(f, "{}", self.category),
+        }
+    }
+}
+
+impl FromStr for CategoryRule {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            Some((category, selector)) => Ok(CategoryRule {
+                category: category.into(),
+                selector: Some(selector.parse()?),
+            }),
+            None => Ok(CategoryRule {
+                category: s.into(),
+                selector: None,
+            }),
+        }
+    }
+}
+
+/// The checks, which are applied to the dependencies.
+#[derive(Default)]
+pub struct Policy {
+    pub score_requirements: Vec<ScoreRequirement>,
+    pub score_overrides: Vec<ScoreOverride>,
+    /// The dependencies, which are not tested.
+    pub ignore: Vec<Selector>,
+    /// The license checks, all of them must accept a license.
+    pub license_checks: Vec<Box<dyn LicenseCheck>>,
+    /// Pass all licenses, only choosing the effective license.
+    pub approve_all: bool,
+    /// Check the crate metadata, which must have been looked up already.
+    pub check_metadata: bool,
+    /// Turn all warnings into failures.
+    pub deny_warnings: bool,
+    /// Parse SPDX expressions in lax mode.
+    pub lax: bool,
+}
+
+impl Policy {
+    /// Evaluate the policy for all dependencies, recording the outcomes and reasons.
+    ///
+    /// The definitions, the previous versions, and the crate metadata must have been looked up
+    /// before.
+    pub fn evaluate(&self, dependencies: &mut [Dependency]) {
+        for dep in dependencies {
+            self.evaluate_dependency(dep);
+        }
+    }
+
+    fn evaluate_dependency(&self, dep: &mut Dependency) {
+        if let Some(cd) = dep
+            .clearly_defined
+            .as_ref()
+            .filter(|cd| !cd.curated.is_empty())
+        {
+            let reason = format!("Locally curated: {}", cd.curated.join(", "));
+            dep.add_reason(Check::Curation, reason);
+        }
+
+        if self
+            .ignore
+            .iter()
+            .any(|s| s.matches(&dep.name, &dep.version))
+        {
+            dep.passed_score = vec![Outcome::Ignore; self.score_requirements.len()];
+        } else {
+            // check license change
+            if let Some(changed) = dep.license_changed() {
+                dep.passed_license_change = (!changed).into();
+            }
+            if let (Some(true), Some(prev)) = (dep.license_changed(), &dep.previous) {
+                let reason = format!(
+                    "Declared license changed from {} ({})",
+                    prev.declared_license.as_deref().unwrap_or("none"),
+                    prev.version
+                );
+                dep.add_reason(Check::LicenseChange, reason);
+            }
+            // check crate metadata
+            if self.check_metadata {
+                dep.passed_metadata = match (&dep.metadata, dep.test_metadata(self.lax)) {
+                    (None, _) => {
+                        dep.add_reason(Check::Metadata, "No crate metadata found");
+                        Outcome::Warn
+                    }
+                    (_, Ok(())) => Outcome::Pass,
+                    (_, Err(err)) => {
+                        dep.add_reason(Check::Metadata, err.to_string());
+                        Outcome::Fail
+                    }
+                };
+            }
+            // check scores
+            let score_override = self
+                .score_overrides
+                .iter()
+                .find(|o| o.selector.matches(&dep.name, &dep.version));
+            for req in &self.score_requirements {
+                let score = dep
+                    .clearly_defined
+                    .as_ref()
+                    .map(|cd| cd.score(req.score_type))
+                    .unwrap_or(0);
+                let (outcome, reason) = req.test(score, score_override);
+                if let Some(reason) = reason {
+                    dep.add_reason(Check::Score, reason);
+                }
+                dep.passed_score.push(outcome);
+            }
+            // check license
+            if self.license_checks.is_empty() {
+                dep.passed_license = Outcome::Fail;
+                dep.add_reason(Check::License, "No license checks configured");
+            } else if self.approve_all {
+                dep.passed_license = Outcome::Pass;
+                dep.effective_license = dep
+                    .test_license(self.lax, &[])
+                    .ok()
+                    .map(|(_, licenses)| format_licenses(&licenses));
+            } else {
+                match dep.test_license(self.lax, &self.license_checks) {
+                    Ok((outcome, licenses)) => {
+                        let effective_license = format_licenses(&licenses);
+                        if let Outcome::Warn = outcome {
+                            let reason = format!(
+                                "{} is only approved by choosing {}",
+                                dep.declared_license().unwrap_or_default(),
+                                effective_license
+                            );
+                            dep.add_reason(Check::License, reason);
+                        }
+                        dep.passed_license = outcome;
+                        dep.effective_license = Some(effective_license);
+                    }
+                    Err(errors) => {
+                        dep.passed_license = Outcome::Fail;
+                        for error in errors {
+                            dep.add_reason(Check::License, error.to_string());
+                        }
+                    }
+                }
+            }
+        }
+
+        if self.deny_warnings {
+            dep.deny_warnings();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{ApprovedLicenses, ClearlyDefined, License};
+    use semver::Version;
+
+    #[test]
+    fn score_override() {
+        let req = ScoreRequirement {
+            score_type: ScoreType::Effective,
+            score: 50,
+            warn: Some(70),
+        };
+        let raised: ScoreOverride = "b=95".parse().unwrap();
+        let lowered: ScoreOverride = "b=30".parse().unwrap();
+        let outcome = |score, o| req.test(score, o).0;
+
+        assert!(matches!(outcome(60, None), Outcome::Warn));
+        assert!(matches!(outcome(80, None), Outcome::Pass));
+        assert!(matches!(outcome(40, None), Outcome::Fail));
+        assert!(matches!(outcome(60, Some(&raised)), Outcome::Fail));
+        assert!(matches!(outcome(95, Some(&raised)), Outcome::Pass));
+        assert!(matches!(outcome(40, Some(&lowered)), Outcome::Override));
+        assert!(matches!(outcome(60, Some(&lowered)), Outcome::Warn));
+        assert!(matches!(outcome(20, Some(&lowered)), Outcome::Fail));
+        assert_eq!(
+            req.test(60, Some(&raised)).1.as_deref(),
+            Some("The effective score of 60 is below 95")
+        );
+    }
+
+    #[test]
+    fn evaluate_policy() {
+        let dependency = |name: &str, license: &str, score| {
+            let mut dep = Dependency::new(name.into(), Version::new(1, 0, 0));
+            dep.clearly_defined = Some(ClearlyDefined::new(
+                Some(License::new(license.into()).unwrap()),
+                score,
+                score,
+                vec![],
+            ));
+            dep
+        };
+
+        let policy = Policy {
+            score_requirements: vec![ScoreRequirement {
+                score_type: ScoreType::Effective,
+                score: 80,
+                warn: None,
+            }],
+            ignore: vec!["c".parse().unwrap()],
+            license_checks: vec![Box::new(ApprovedLicenses {
+                licenses: vec![spdx::license_id("MIT").unwrap()],
+            })],
+            ..Default::default()
+        };
+
+        let mut deps = vec![
+            dependency("a", "MIT OR Apache-2.0", 90),
+            dependency("b", "Apache-2.0", 50),
+            dependency("c", "Apache-2.0", 50),
+        ];
+        policy.evaluate(&mut deps);
+
+        assert!(deps[0].passed());
+        assert!(matches!(deps[0].passed_license, Outcome::Warn));
+        assert_eq!(deps[0].effective_license.as_deref(), Some("MIT"));
+
+        assert!(!deps[1].passed());
+        assert_eq!(
+            deps[1].reasons_of(Check::Score),
+            vec!["The effective score of 50 is below 80"]
+        );
+
+        assert!(deps[2].passed());
+        assert!(matches!(deps[2].passed_score[..], [Outcome::Ignore]));
+    }
+}
@@ -11,13 +11,28 @@
  * SPDX-License-Identifier: EPL-2.0
  */
 
-use crate::args::{Args, OutputFormat};
-use crate::data::{Dependency, Outcome};
-use anyhow::Result;
+use crate::data::{CrateMetadata, Dependency, LicenseCheck, Outcome};
+use crate::diff::DiffEntry;
+use crate::pattern::Selector;
+use crate::policy::{CategoryRule, ScoreOverride, ScoreRequirement, ScoreType};
+use anyhow::{anyhow, Result};
+use clap::ValueEnum;
 use prettytable::csv::Writer;
 use prettytable::format::{self, FormatBuilder};
 use prettytable::{Cell, Row, Table};
-use std::io;
+use spdx::LicenseId;
+use std::io::Write;
+use std::path::PathBuf;
+use std::slice;
+
+mod gitlab;
+mod html;
+pub mod model;
+mod sarif;
+mod summary;
+mod template;
+
+pub use summary::{overall, Summary};
 
 const ERR_PREFIX: &str = "ERR: ";
 #[cfg(any(not(windows), not(feature = "win_crlf")))]
@@ -41,9 +56,11 @@ fn shield_escape(input: &str) -> String {
     input.replace('-', "--").replace('_', "__")
 }
 
-fn shield_score(dep: &Dependency, score: &String) -> String {
-    let passed = match dep.passed_score {
+fn shield_score(dep: &Dependency, score: &String, outcome: Outcome) -> String {
+    let passed = match outcome {
         Outcome::Pass => "success",
+        Outcome::Override => "yellowgreen",
+        Outcome::Warn => "yellow",
         Outcome::Fail => "critical",
         Outcome::Ignore => "inactive",
     };
@@ -79,13 +96,15 @@ where
             s.replace(NEWLINE, ERR_PREFIX_NEWLINE)
         }
         OutputFormat::Markdown => format!("<b>ERR:</b> <i>{}</i>", err.to_string()),
-        OutputFormat::Csv => format!("ERR: {}", err.to_string()),
+        _ => format!("ERR: {}", err.to_string()),
     }
 }
 
 fn emoji(outcome: Outcome) -> &'static str {
     match outcome {
         Outcome::Pass => "✅",
+        Outcome::Override => "☑️",
+        Outcome::Warn => "⚠️",
         Outcome::Fail => "❌",
         Outcome::Ignore => "🙈",
     }
@@ -94,18 +113,207 @@ fn emoji(outcome: Outcome) -> &'static str {
 fn csv(outcome: Outcome) -> &'static str {
     match outcome {
         Outcome::Pass => "+",
+        Outcome::Override => "~",
+        Outcome::Warn => "!",
         Outcome::Fail => "-",
         Outcome::Ignore => "",
     }
 }
 
+fn score_title(title: &str, requirements: &[ScoreRequirement], req: &ScoreRequirement) -> String {
+    match requirements.len() {
+        1 => title.to_string(),
+        _ => format!("{} ({})", title, req.score_type),
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum OutputFormat {
+    Text,
+    Csv,
+    Markdown,
+    Html,
+    Template,
+    Json,
+    Sarif,
+    /// GitLab license scanning report (`gl-license-scanning-report.json`)
+    Gitlab,
+}
+
+/// The checks which were performed, selecting the columns of the report.
+pub struct Checks<'a> {
+    pub score_requirements: &'a [ScoreRequirement],
+    pub license: bool,
+    /// The configured license checks, not applied if all licenses are approved.
+    pub license_checks: &'a [Box<dyn LicenseCheck>],
+    pub license_change: bool,
+    pub metadata: bool,
+}
+
+/// A section of the report, with an optional title.
+pub struct Section<'a> {
+    pub title: Option<&'a str>,
+    pub dependencies: &'a [Dependency],
+}
+
+/// The options of the report, and the policy it was checked with.
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    /// The checked lockfile.
+    pub input: PathBuf,
+    /// Parse SPDX expressions in lax mode.
+    pub lax: bool,
+    /// Add a link to the definition at clearlydefined.io.
+    pub link: bool,
+    /// The template to render, when using the template output format.
+    pub template: Option<PathBuf>,
+    pub score_overrides: Vec<ScoreOverride>,
+    pub approve_all: bool,
+    pub approve_osi: bool,
+    pub approved_licenses: Vec<LicenseId>,
+    pub allowed_categories: Vec<CategoryRule>,
+    pub denied_categories: Vec<CategoryRule>,
+    pub deny_warnings: bool,
+    /// The dependencies, which were excluded completely.
+    pub exclude: Vec<Selector>,
+    /// The dependencies, which were ignored when testing.
+    pub ignore: Vec<Selector>,
+}
+
+/// Show the report, in the requested format.
 pub fn show(
+    out: &mut dyn Write,
     format: OutputFormat,
-    args: &Args,
-    show_score_check: bool,
-    show_license_check: bool,
-    dependencies: &Vec<Dependency>,
+    options: &Options,
+    checks: &Checks,
+    sections: &[Section],
+    summary: &Summary,
 ) -> Result<()> {
+    match format {
+        OutputFormat::Html => return html::show(out, options, checks, sections, summary),
+        OutputFormat::Template => return template::show(out, options, checks, sections, summary),
+        OutputFormat::Json => {
+            let report = model::Report::new(options, checks, sections, summary);
+            serde_json::to_writer_pretty(&mut *out, &report)?;
+            writeln!(out)?;
+            return Ok(());
+        }
+        OutputFormat::Sarif => return sarif::show(out, options, checks, sections, summary),
+        OutputFormat::Gitlab => return gitlab::show(out, options, checks, sections),
+        OutputFormat::Text | OutputFormat::Csv | OutputFormat::Markdown => {}
+    }
+
+    // keep the CSV output a single table, without the summary
+    if let OutputFormat::Csv = format {
+        return print(out, format, table(format, options, checks, sections));
+    }
+
+    for section in sections {
+        if let Some(title) = section.title {
+            heading(out, format, title)?;
+        }
+
+        print(
+            out,
+            format,
+            table(format, options, checks, slice::from_ref(section)),
+        )?;
+    }
+
+    heading(out, format, "Summary")?;
+    for (i, table) in summary_tables(summary).into_iter().enumerate() {
+        if i > 0 {
+            writeln!(out)?;
+        }
+        print(out, format, table)?;
+    }
+
+    Ok(())
+}
+
+fn heading(out: &mut dyn Write, format: OutputFormat, title: &str) -> Result<()> {
+    match format {
+        OutputFormat::Text => writeln!(out, "{}{}:", NEWLINE, title)?,
+        OutputFormat::Markdown => writeln!(out, "{}### {}{}", NEWLINE, title, NEWLINE)?,
+        _ => {}
+    }
+    Ok(())
+}
+
+/// The summary, as tables of totals, the license distribution, and the score histogram.
+fn summary_tables(summary: &Summary) -> Vec<Table> {
+    let mut totals = Table::new();
+    totals.set_titles(Row::new(vec![
+        Cell::new("Dependencies"),
+        Cell::new("Count"),
+    ]));
+    for (title, count) in [
+        ("Total", summary.total),
+        ("Passed", summary.outcomes.pass),
+        ("Overridden", summary.outcomes.overridden),
+        ("Warnings", summary.outcomes.warn),
+        ("Failed", summary.outcomes.fail),
+        ("Ignored", summary.ignored),
+        ("Excluded", summary.excluded),
+        ("No definition", summary.missing_definition),
+        ("Locally curated", summary.curated),
+        ("Missing license", summary.missing_license),
+        ("Unparsable license", summary.unparsable_license),
+    ] {
+        totals.add_row(Row::new(vec![
+            Cell::new(title),
+            Cell::new(&count.to_string()),
+        ]));
+    }
+
+    let mut licenses = Table::new();
+    licenses.set_titles(Row::new(vec![Cell::new("License"), Cell::new("Count")]));
+    for (license, count) in summary.licenses_by_count() {
+        licenses.add_row(Row::new(vec![
+            Cell::new(license),
+            Cell::new(&count.to_string()),
+        ]));
+    }
+
+    let mut tables = vec![totals, licenses];
+
+    if !summary.scores.is_empty() {
+        let mut scores = Table::new();
+        let mut titles = vec![Cell::new("Score")];
+        for histogram in &summary.scores {
+            titles.push(Cell::new(&histogram.score_type.to_string()));
+        }
+        scores.set_titles(Row::new(titles));
+        for bucket in 0..summary.scores[0].buckets.len() {
+            let mut row = vec![Cell::new(&summary::bucket_title(bucket))];
+            for histogram in &summary.scores {
+                row.push(Cell::new(&histogram.buckets[bucket].to_string()));
+            }
+            scores.add_row(Row::new(row));
+        }
+        tables.push(scores);
+    }
+
+    tables
+}
+
+/// The declared license of the dependency, normalized if it can be parsed.
+fn declared_license(dep: &Dependency, lax: bool) -> Result<Option<String>> {
+    dep.clearly_defined
+        .as_ref()
+        .and_then(|cd| cd.declared_license.as_ref())
+        .map(|l| l.expression(lax).map(|e| e.to_string()))
+        .transpose()
+}
+
+/// The table of the dependencies. With multiple sections, a column shows the section of each row.
+fn table(format: OutputFormat, options: &Options, checks: &Checks, sections: &[Section]) -> Table {
+    let dependencies = || sections.iter().flat_map(|s| s.dependencies);
+
+    let score_requirements = checks.score_requirements;
+    let show_license_check = checks.license;
+    let show_license_change_check = checks.license_change;
+
     let mut table = Table::new();
 
     let mut titles = vec![
@@ -114,16 +322,50 @@ pub fn show(
         Cell::new("Declared license"),
     ];
 
+    let show_effective_license = dependencies().any(|dep| dep.effective_license.is_some());
+
+    if show_effective_license {
+        titles.push(Cell::new("Effective license"))
+    }
+
     if show_license_check {
         titles.push(Cell::new("License"))
     }
 
-    titles.push(Cell::new("Score"));
+    if show_license_change_check {
+        titles.push(Cell::new("License change"))
+    }
 
-    #[allow(clippy::single_match)]
-    match (&format, show_score_check) {
-        (OutputFormat::Csv, true) => titles.push(Cell::new("Score check")),
-        _ => {}
+    for req in score_requirements {
+        titles.push(Cell::new(&score_title("Score", score_requirements, req)));
+
+        #[allow(clippy::single_match)]
+        match (&format, req.score > 0) {
+            (OutputFormat::Csv, true) => titles.push(Cell::new(&score_title(
+                "Score check",
+                score_requirements,
+                req,
+            ))),
+            _ => {}
+        }
+    }
+
+    let show_metadata = checks.metadata;
+
+    if show_metadata {
+        titles.push(Cell::new("Crate license"));
+    }
+
+    let show_reasons = dependencies().any(|dep| !dep.reasons.is_empty());
+
+    if show_reasons {
+        titles.push(Cell::new("Reason"));
+    }
+
+    let show_section = sections.len() > 1;
+
+    if show_section {
+        titles.push(Cell::new("Section"));
     }
 
     // set title
@@ -132,45 +374,16 @@ pub fn show(
 
     // start iterating
 
-    let lax = args.lax;
-    let link = args.link;
-
-    for dep in dependencies {
-        let (license_str, score) = dep
-            .clearly_defined
-            .as_ref()
-            .map(|cd| {
-                let score = format!("{}", cd.score(args.score_type));
-
-                let l = cd
-                    .declared_license
-                    .as_ref()
-                    .map(|l| l.expression(lax))
-                    .transpose();
-
-                match l {
-                    Ok(Some(license)) => (license.to_string(), score),
-                    Err(parse_err) => (format_error(format, parse_err), score),
-                    _ => ("".into(), score),
-                }
-            })
-            .unwrap_or(("".into(), "".into()));
-
-        let score = match (&format, link, show_score_check, dep.passed_score) {
-            (OutputFormat::Markdown, true, false, _) => {
-                format!("[{}]({})", score, clearly_link(dep))
-            }
-            (OutputFormat::Markdown, true, true, _) => {
-                markdown_image_link(&shield_score(dep, &score), &clearly_link(dep), &score)
-            }
-            (OutputFormat::Markdown, _, true, outcome) => format!("{} {}", emoji(outcome), score),
-            (OutputFormat::Text, false, true, outcome) => format!("{} {}", emoji(outcome), score),
-            (OutputFormat::Text, true, true, outcome) => {
-                format!("{} {} ({})", emoji(outcome), score, clearly_link(dep))
-            }
+    let lax = options.lax;
+    let link = options.link;
 
-            // all other variant only show the score
-            _ => score,
+    for (section, dep) in sections
+        .iter()
+        .flat_map(|s| s.dependencies.iter().map(move |dep| (s, dep)))
+    {
+        let license_str = match declared_license(dep, lax) {
+            Ok(license) => license.unwrap_or_default(),
+            Err(parse_err) => format_error(format, parse_err),
         };
 
         // default rows
@@ -181,28 +394,124 @@ pub fn show(
             Cell::new(&license_str),
         ];
 
+        // effective license column
+
+        if show_effective_license {
+            row.push(Cell::new(
+                dep.effective_license.as_deref().unwrap_or_default(),
+            ));
+        }
+
         // license test column
 
         if show_license_check {
-            if let Some(cell) = match (&format, dep.passed_license) {
-                (OutputFormat::Csv, outcome) => Some(csv(outcome)),
-                (OutputFormat::Text, outcome) => Some(emoji(outcome)),
-                (OutputFormat::Markdown, outcome) => Some(emoji(outcome)),
-            } {
-                row.push(Cell::new(cell))
+            let cell = match format {
+                OutputFormat::Csv => csv(dep.passed_license),
+                _ => emoji(dep.passed_license),
+            };
+            row.push(Cell::new(cell));
+        }
+
+        // license change column
+
+        if show_license_change_check {
+            let outcome = match format {
+                OutputFormat::Csv => csv(dep.passed_license_change),
+                _ => emoji(dep.passed_license_change),
+            };
+            let cell = match (&dep.previous, dep.license_changed()) {
+                (Some(prev), Some(true)) => format!(
+                    "{} {} ({})",
+                    outcome,
+                    prev.declared_license.as_deref().unwrap_or("none"),
+                    prev.version
+                ),
+                _ => outcome.to_string(),
+            };
+            row.push(Cell::new(&cell));
+        }
+
+        for (i, req) in score_requirements.iter().enumerate() {
+            let show_score_check = req.score > 0;
+            let passed_score = dep.passed_score.get(i).copied().unwrap_or(Outcome::Ignore);
+
+            let score = dep
+                .clearly_defined
+                .as_ref()
+                .map(|cd| format!("{}", cd.score(req.score_type)))
+                .unwrap_or_default();
+
+            let score = match (&format, link, show_score_check, passed_score) {
+                (OutputFormat::Markdown, true, false, _) => {
+                    format!("[{}]({})", score, clearly_link(dep))
+                }
+                (OutputFormat::Markdown, true, true, outcome) => markdown_image_link(
+                    &shield_score(dep, &score, outcome),
+                    &clearly_link(dep),
+                    &score,
+                ),
+                (OutputFormat::Markdown, _, true, outcome) => {
+                    format!("{} {}", emoji(outcome), score)
+                }
+                (OutputFormat::Text, false, true, outcome) => {
+                    format!("{} {}", emoji(outcome), score)
+                }
+                (OutputFormat::Text, true, true, outcome) => {
+                    format!("{} {} ({})", emoji(outcome), score, clearly_link(dep))
+                }
+
+                // all other variant only show the score
+                _ => score,
+            };
+
+            // add score
+
+            row.push(Cell::new(&score));
+
+            // add test column
+
+            #[allow(clippy::single_match)]
+            match (&format, show_score_check, passed_score) {
+                (OutputFormat::Csv, true, outcome) => row.push(Cell::new(csv(outcome))),
+                _ => {}
             }
         }
 
-        // add score
+        // crate metadata column
+
+        if show_metadata {
+            let outcome = match format {
+                OutputFormat::Csv => csv(dep.passed_metadata),
+                _ => emoji(dep.passed_metadata),
+            };
+            let license = match &dep.metadata {
+                Some(CrateMetadata {
+                    license: Some(license),
+                    ..
+                }) => license.as_str(),
+                Some(CrateMetadata {
+                    license_file: true, ..
+                }) => "license-file",
+                _ => "",
+            };
+            row.push(Cell::new(&format!("{} {}", outcome, license)));
+        }
 
-        row.push(Cell::new(&score));
+        // reason column
 
-        // add test column
+        if show_reasons {
+            let separator = match format {
+                OutputFormat::Markdown => "<br>",
+                OutputFormat::Csv => "; ",
+                _ => NEWLINE,
+            };
+            row.push(Cell::new(&dep.reason_messages().join(separator)));
+        }
 
-        #[allow(clippy::single_match)]
-        match (&format, show_score_check, dep.passed_score) {
-            (OutputFormat::Csv, true, outcome) => row.push(Cell::new(csv(outcome))),
-            _ => {}
+        // section column
+
+        if show_section {
+            row.push(Cell::new(section.title.unwrap_or_default()));
         }
 
         // add row
@@ -210,15 +519,17 @@ pub fn show(
         table.add_row(Row::new(row));
     }
 
-    // print result
+    table
+}
 
+fn print(out: &mut dyn Write, format: OutputFormat, mut table: Table) -> Result<()> {
     match format {
         OutputFormat::Csv => {
-            table.to_csv_writer(Writer::from_writer(io::stdout()))?;
+            table.to_csv_writer(Writer::from_writer(out))?;
         }
         OutputFormat::Text => {
             table.set_format(*prettytable::format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
-            table.printstd();
+            table.print(out)?;
         }
         OutputFormat::Markdown => {
             let format = FormatBuilder::new()
@@ -232,9 +543,120 @@ pub fn show(
                 .build();
 
             table.set_format(format);
-            table.printstd();
+            table.print(out)?;
+        }
+        OutputFormat::Html => {
+            html::show_table(out, &table)?;
+        }
+        OutputFormat::Template
+        | OutputFormat::Json
+        | OutputFormat::Sarif
+        | OutputFormat::Gitlab => {
+            return Err(anyhow!(
+                "The {:?} output format is not supported here",
+                format
+            ));
         }
     }
 
     Ok(())
 }
+
+fn score_delta(entry: &DiffEntry, score_type: ScoreType) -> String {
+    let score = |dep: &Option<Dependency>| {
+        dep.as_ref()
+            .and_then(|dep| dep.clearly_defined.as_ref())
+            .map(|cd| cd.score(score_type))
+    };
+
+    match (score(&entry.old), score(&entry.new)) {
+        (Some(old), Some(new)) => format!("{} → {} ({:+})", old, new, new as i64 - old as i64),
+        (None, Some(score)) | (Some(score), None) => score.to_string(),
+        (None, None) => "".into(),
+    }
+}
+
+/// Check if the output format can show the changes between two lockfiles.
+pub fn supports_diff(format: OutputFormat) -> bool {
+    !matches!(
+        format,
+        OutputFormat::Template | OutputFormat::Sarif | OutputFormat::Gitlab
+    )
+}
+
+/// Show the changes between two lockfiles.
+pub fn show_diff(
+    out: &mut dyn Write,
+    format: OutputFormat,
+    score_types: &[ScoreType],
+    entries: &[DiffEntry],
+) -> Result<()> {
+    if let OutputFormat::Json = format {
+        let report = model::DiffReport::new(score_types, entries);
+        serde_json::to_writer_pretty(&mut *out, &report)?;
+        writeln!(out)?;
+        return Ok(());
+    }
+    if !supports_diff(format) {
+        return Err(anyhow!(
+            "The {:?} output format doesn't support comparing lockfiles",
+            format
+        ));
+    }
+
+    let mut table = Table::new();
+
+    let mut titles = vec![
+        Cell::new("Change"),
+        Cell::new("Name"),
+        Cell::new("Old version"),
+        Cell::new("New version"),
+        Cell::new("Old license"),
+        Cell::new("New license"),
+        Cell::new("License changed"),
+    ];
+
+    for score_type in score_types {
+        titles.push(Cell::new(&format!("Score ({})", score_type)));
+    }
+
+    table.set_titles(Row::new(titles));
+
+    for entry in entries {
+        let version = |dep: &Option<Dependency>| {
+            dep.as_ref()
+                .map(|dep| dep.version.to_string())
+                .unwrap_or_default()
+        };
+        let license = |dep: &Option<Dependency>| {
+            dep.as_ref()
+                .and_then(|dep| dep.declared_license())
+                .unwrap_or_default()
+                .to_string()
+        };
+
+        let license_changed = match (format, entry.license_changed()) {
+            (OutputFormat::Csv, true) => "+",
+            (_, true) => "⚠️",
+            (_, false) => "",
+        };
+
+        let mut row = vec![
+            Cell::new(&entry.change().to_string()),
+            Cell::new(&entry.name),
+            Cell::new(&version(&entry.old)),
+            Cell::new(&version(&entry.new)),
+            Cell::new(&license(&entry.old)),
+            Cell::new(&license(&entry.new)),
+            Cell::new(license_changed),
+        ];
+
+        for score_type in score_types {
+            row.push(Cell::new(&score_delta(entry, *score_type)));
+        }
+
+        table.add_row(Row::new(row));
+    }
+
+    print(out, format, table)
+}
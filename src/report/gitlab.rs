@@ -0,0 +1,240 @@
+/*
+ * Copyright (c) 2020 Red Hat Inc.
+ *
+ * See the NOTICE file(s) distributed with this work for additional
+ * information regarding copyright ownership.
+ *
+ * This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License 2.0 which is available at
+ * http://www.eclipse.org/legal/epl-2.0
+ *
+ * SPDX-License-Identifier: EPL-2.0
+ */
+
+//! A GitLab license scanning report (`gl-license-scanning-report.json`, version 2.1).
+
+use super::{Checks, Options, Section};
+use crate::data::{Dependency, Outcome};
+use anyhow::Result;
+use serde::Serialize;
+use spdx::{Expression, LicenseItem, LicenseReq, ParseMode};
+use std::collections::BTreeMap;
+use std::io::Write;
+
+const VERSION: &str = "2.1";
+const PACKAGE_MANAGER: &str = "cargo";
+
+#[derive(Debug, Serialize)]
+struct Report {
+    version: &'static str,
+    licenses: Vec<License>,
+    dependencies: Vec<GitlabDependency>,
+}
+
+#[derive(Debug, Serialize)]
+struct License {
+    id: String,
+    name: String,
+    url: String,
+    /// The outcome of the license check: `allowed`, `denied`, or `unclassified` if not checked
+    approval_status: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+struct GitlabDependency {
+    name: String,
+    version: String,
+    package_manager: &'static str,
+    path: String,
+    licenses: Vec<String>,
+}
+
+/// The licenses of the dependency: the effective license if one was chosen, the declared license
+/// otherwise. Licenses which can't be parsed are reported as-is.
+fn dependency_licenses(dep: &Dependency, lax: bool) -> Vec<(String, Option<LicenseReq>)> {
+    let raw = match (&dep.effective_license, dep.declared_license()) {
+        (Some(effective), _) => effective.as_str(),
+        (None, Some(declared)) => declared,
+        (None, None) => return vec![],
+    };
+
+    let mode = match lax {
+        true => ParseMode::LAX,
+        false => ParseMode::STRICT,
+    };
+
+    match Expression::parse_mode(raw, mode) {
+        Ok(expression) => {
+            let mut result: Vec<(String, Option<LicenseReq>)> = Vec::new();
+            for req in expression.requirements() {
+                let id = req.req.to_string();
+                if !result.iter().any(|(i, _)| i == &id) {
+                    result.push((id, Some(req.req.clone())));
+                }
+            }
+            result
+        }
+        Err(_) => vec![(raw.to_string(), None)],
+    }
+}
+
+/// The outcome of the license checks for a single license of the dependency.
+fn approval_status(
+    checks: &Checks,
+    dep: &Dependency,
+    license: Option<&LicenseReq>,
+) -> &'static str {
+    if let Outcome::Ignore = dep.passed_license {
+        return "unclassified";
+    }
+    if !checks.license {
+        return "allowed";
+    }
+
+    match license {
+        _ if checks.license_checks.is_empty() => "unclassified",
+        Some(license) => match checks
+            .license_checks
+            .iter()
+            .all(|check| check.accepts(dep, license))
+        {
+            true => "allowed",
+            false => "denied",
+        },
+        None => "unclassified",
+    }
+}
+
+/// Show all dependencies as a GitLab license scanning report.
+pub fn show(
+    out: &mut dyn Write,
+    options: &Options,
+    checks: &Checks,
+    sections: &[Section],
+) -> Result<()> {
+    let path = options.input.display().to_string();
+
+    let mut licenses = BTreeMap::<String, License>::new();
+    let mut dependencies = Vec::new();
+
+    for dep in sections.iter().flat_map(|s| s.dependencies) {
+        let dep_licenses = dependency_licenses(dep, options.lax);
+
+        for (id, req) in &dep_licenses {
+            let status = approval_status(checks, dep, req.as_ref());
+            let license = licenses.entry(id.clone()).or_insert_with(|| {
+                let (name, url) = match req.as_ref().map(|r| &r.license) {
+                    Some(LicenseItem::Spdx { id, .. }) => (
+                        id.full_name.to_string(),
+                        format!("https://spdx.org/licenses/{}.html", id.name),
+                    ),
+                    _ => (id.clone(), String::new()),
+                };
+                License {
+                    id: id.clone(),
+                    name,
+                    url,
+                    approval_status: status,
+                }
+            });
+
+            // a license allowed for one dependency is allowed, unless it is denied for another one
+            license.approval_status = match (license.approval_status, status) {
+                ("denied", _) | (_, "denied") => "denied",
+                ("allowed", _) | (_, "allowed") => "allowed",
+                _ => "unclassified",
+            };
+        }
+
+        dependencies.push(GitlabDependency {
+            name: dep.name.clone(),
+            version: dep.version.to_string(),
+            package_manager: PACKAGE_MANAGER,
+            path: path.clone(),
+            licenses: dep_licenses.into_iter().map(|(id, _)| id).collect(),
+        });
+    }
+
+    let report = Report {
+        version: VERSION,
+        licenses: licenses.into_values().collect(),
+        dependencies,
+    };
+
+    serde_json::to_writer_pretty(&mut *out, &report)?;
+    writeln!(out)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{ApprovedLicenses, ClearlyDefined, License, LicenseCheck};
+    use serde_json::Value;
+
+    fn report(declared: &str, license_checks: &[Box<dyn LicenseCheck>]) -> Value {
+        let mut dep = Dependency::new("a".into(), "1.0.0".parse().unwrap());
+        dep.clearly_defined = Some(ClearlyDefined::new(
+            Some(License::new(declared.into()).unwrap()),
+            0,
+            0,
+            vec![],
+        ));
+        dep.passed_license = Outcome::Fail;
+        let deps = [dep];
+
+        let checks = Checks {
+            score_requirements: &[],
+            license: true,
+            license_checks,
+            license_change: false,
+            metadata: false,
+        };
+        let sections = [Section {
+            title: None,
+            dependencies: &deps,
+        }];
+
+        let mut out = Vec::new();
+        show(&mut out, &Options::default(), &checks, &sections).unwrap();
+        serde_json::from_slice(&out).unwrap()
+    }
+
+    fn statuses(report: &Value) -> Vec<(&str, &str)> {
+        report["licenses"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|l| {
+                (
+                    l["id"].as_str().unwrap(),
+                    l["approval_status"].as_str().unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn approval_status_per_license() {
+        let checks: Vec<Box<dyn LicenseCheck>> = vec![Box::new(ApprovedLicenses {
+            licenses: vec![spdx::license_id("MIT").unwrap()],
+        })];
+
+        let report = report("MIT AND Apache-2.0", &checks);
+        assert_eq!(
+            statuses(&report),
+            vec![("Apache-2.0", "denied"), ("MIT", "allowed")]
+        );
+        assert_eq!(
+            report["dependencies"][0]["licenses"],
+            serde_json::json!(["MIT", "Apache-2.0"])
+        );
+    }
+
+    #[test]
+    fn unclassified_without_checks() {
+        let report = report("MIT", &[]);
+        assert_eq!(statuses(&report), vec![("MIT", "unclassified")]);
+    }
+}
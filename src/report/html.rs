@@ -0,0 +1,412 @@
+/*
+ * Copyright (c) 2020 Red Hat Inc.
+ *
+ * See the NOTICE file(s) distributed with this work for additional
+ * information regarding copyright ownership.
+ *
+ * This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License 2.0 which is available at
+ * http://www.eclipse.org/legal/epl-2.0
+ *
+ * SPDX-License-Identifier: EPL-2.0
+ */
+
+//! A self-contained HTML report, without any external assets.
+
+use super::summary::{self, overall, Summary};
+use super::{clearly_link, declared_license, emoji, score_title, Checks, Options, Section};
+use crate::data::{CrateMetadata, Dependency, Outcome};
+use anyhow::Result;
+use prettytable::Table;
+use std::io::Write;
+
+const STYLE: &str = r#"
+body { font-family: sans-serif; margin: 2em; color: #222; }
+table { border-collapse: collapse; width: 100%; margin-bottom: 2em; }
+th, td { border: 1px solid #ccc; padding: 0.3em 0.6em; text-align: left; vertical-align: top; }
+th { background: #eee; cursor: pointer; user-select: none; }
+th[data-order="asc"]::after { content: " ▲"; }
+th[data-order="desc"]::after { content: " ▼"; }
+tbody[data-outcome="fail"] tr.summary { background: #fdecea; }
+tbody[data-outcome="warn"] tr.summary { background: #fff8e1; }
+tr.summary { cursor: pointer; }
+tr.details td { background: #fafafa; }
+table.scores { width: auto; margin: 0.5em 0; }
+table.summary { width: auto; display: inline-table; margin-right: 2em; vertical-align: top; }
+.filter { margin-bottom: 1em; }
+"#;
+
+const SCRIPT: &str = r#"
+document.querySelectorAll("table.report").forEach(function (table) {
+  table.querySelectorAll("th").forEach(function (th, index) {
+    th.addEventListener("click", function () {
+      var asc = th.dataset.order !== "asc";
+      table.querySelectorAll("th").forEach(function (t) { delete t.dataset.order; });
+      th.dataset.order = asc ? "asc" : "desc";
+      var bodies = Array.prototype.slice.call(table.tBodies);
+      bodies.sort(function (a, b) {
+        var x = a.rows[0].cells[index].dataset.value;
+        var y = b.rows[0].cells[index].dataset.value;
+        var r = x.localeCompare(y, undefined, { numeric: true });
+        return asc ? r : -r;
+      });
+      bodies.forEach(function (body) { table.appendChild(body); });
+    });
+  });
+  table.querySelectorAll("tr.summary").forEach(function (row) {
+    row.addEventListener("click", function () {
+      row.nextElementSibling.hidden = !row.nextElementSibling.hidden;
+    });
+  });
+});
+document.querySelectorAll("input[name=filter]").forEach(function (input) {
+  input.addEventListener("change", function () {
+    document.querySelectorAll("tbody.dependency").forEach(function (body) {
+      body.hidden = input.value !== "all" && body.dataset.outcome !== input.value;
+    });
+  });
+});
+"#;
+
+fn escape(input: &str) -> String {
+    let mut result = String::with_capacity(input.len());
+    for c in input.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\'' => result.push_str("&#39;"),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+/// The overall outcome of a dependency, used for filtering.
+fn filter_outcome(dep: &Dependency) -> &'static str {
+    match overall(dep) {
+        Outcome::Fail => "fail",
+        Outcome::Warn => "warn",
+        Outcome::Pass | Outcome::Override | Outcome::Ignore => "pass",
+    }
+}
+
+fn header(out: &mut dyn Write, title: &str) -> Result<()> {
+    writeln!(out, "<!DOCTYPE html>")?;
+    writeln!(out, "<html lang=\"en\">")?;
+    writeln!(out, "<head>")?;
+    writeln!(out, "<meta charset=\"utf-8\">")?;
+    writeln!(out, "<title>{}</title>", escape(title))?;
+    writeln!(out, "<style>{}</style>", STYLE)?;
+    writeln!(out, "</head>")?;
+    writeln!(out, "<body>")?;
+    writeln!(out, "<h1>{}</h1>", escape(title))?;
+    Ok(())
+}
+
+fn footer(out: &mut dyn Write) -> Result<()> {
+    writeln!(out, "<script>{}</script>", SCRIPT)?;
+    writeln!(out, "</body>")?;
+    writeln!(out, "</html>")?;
+    Ok(())
+}
+
+fn cell(out: &mut dyn Write, value: &str, content: &str) -> Result<()> {
+    write!(
+        out,
+        "<td data-value=\"{}\">{}</td>",
+        escape(value),
+        escape(content)
+    )?;
+    Ok(())
+}
+
+fn details(out: &mut dyn Write, dep: &Dependency, columns: usize) -> Result<()> {
+    writeln!(
+        out,
+        "<tr class=\"details\" hidden><td colspan=\"{}\">",
+        columns
+    )?;
+
+    let link = clearly_link(dep);
+    writeln!(
+        out,
+        "<p><a href=\"{}\">{}</a></p>",
+        escape(&link),
+        escape(&link)
+    )?;
+
+    if let Some(cd) = &dep.clearly_defined {
+        writeln!(out, "<table class=\"scores\">")?;
+        writeln!(out, "<tr><th>Score</th><th>Value</th></tr>")?;
+        for (name, value) in &cd.score_details {
+            writeln!(out, "<tr><td>{}</td><td>{}</td></tr>", escape(name), value)?;
+        }
+        writeln!(out, "</table>")?;
+    }
+
+    if let Some(cd) = dep
+        .clearly_defined
+        .as_ref()
+        .filter(|cd| !cd.curated.is_empty())
+    {
+        writeln!(
+            out,
+            "<p>Locally curated: {}</p>",
+            escape(&cd.curated.join(", "))
+        )?;
+    }
+
+    if !dep.curations.is_empty() {
+        writeln!(out, "<p>Upstream curations:</p>")?;
+        writeln!(out, "<ul>")?;
+        for curation in &dep.curations {
+            writeln!(
+                out,
+                "<li><a href=\"{}\">#{} {}</a>{}</li>",
+                escape(&curation.url),
+                curation.number,
+                escape(&curation.title),
+                if curation.merged { " (merged)" } else { "" }
+            )?;
+        }
+        writeln!(out, "</ul>")?;
+    }
+
+    if let Some(license) = &dep.effective_license {
+        writeln!(out, "<p>Effective license: {}</p>", escape(license))?;
+    }
+
+    if let Some(prev) = &dep.previous {
+        writeln!(
+            out,
+            "<p>Previous version: {} ({})</p>",
+            escape(&prev.version.to_string()),
+            escape(prev.declared_license.as_deref().unwrap_or("none"))
+        )?;
+    }
+
+    if !dep.reasons.is_empty() {
+        writeln!(out, "<ul>")?;
+        for reason in &dep.reasons {
+            writeln!(out, "<li>{}</li>", escape(&reason.message))?;
+        }
+        writeln!(out, "</ul>")?;
+    }
+
+    writeln!(out, "</td></tr>")?;
+
+    Ok(())
+}
+
+fn section(
+    out: &mut dyn Write,
+    options: &Options,
+    checks: &Checks,
+    dependencies: &[Dependency],
+) -> Result<()> {
+    let score_requirements = checks.score_requirements;
+    let show_effective_license = dependencies
+        .iter()
+        .any(|dep| dep.effective_license.is_some());
+
+    let mut titles = vec![
+        "Name".to_string(),
+        "Version".into(),
+        "Declared license".into(),
+    ];
+    if show_effective_license {
+        titles.push("Effective license".into());
+    }
+    if checks.license {
+        titles.push("License".into());
+    }
+    if checks.license_change {
+        titles.push("License change".into());
+    }
+    for req in score_requirements {
+        titles.push(score_title("Score", score_requirements, req));
+    }
+    if checks.metadata {
+        titles.push("Crate license".into());
+    }
+    titles.push("Reason".into());
+
+    writeln!(out, "<table class=\"report\">")?;
+    write!(out, "<thead><tr>")?;
+    for title in &titles {
+        write!(out, "<th>{}</th>", escape(title))?;
+    }
+    writeln!(out, "</tr></thead>")?;
+
+    for dep in dependencies {
+        writeln!(
+            out,
+            "<tbody class=\"dependency\" data-outcome=\"{}\">",
+            filter_outcome(dep)
+        )?;
+        write!(out, "<tr class=\"summary\">")?;
+
+        cell(out, &dep.name, &dep.name)?;
+        cell(out, &dep.version.to_string(), &dep.version.to_string())?;
+
+        let license = match declared_license(dep, options.lax) {
+            Ok(license) => license.unwrap_or_default(),
+            Err(err) => format!("ERR: {}", err),
+        };
+        cell(out, &license, &license)?;
+
+        if show_effective_license {
+            let license = dep.effective_license.as_deref().unwrap_or_default();
+            cell(out, license, license)?;
+        }
+
+        if checks.license {
+            let outcome = emoji(dep.passed_license);
+            cell(out, outcome, outcome)?;
+        }
+
+        if checks.license_change {
+            let outcome = emoji(dep.passed_license_change);
+            cell(out, outcome, outcome)?;
+        }
+
+        for (i, req) in score_requirements.iter().enumerate() {
+            let score = dep
+                .clearly_defined
+                .as_ref()
+                .map(|cd| cd.score(req.score_type).to_string())
+                .unwrap_or_default();
+            let outcome = dep.passed_score.get(i).copied().unwrap_or(Outcome::Ignore);
+            match req.score > 0 {
+                true => cell(out, &score, &format!("{} {}", emoji(outcome), score))?,
+                false => cell(out, &score, &score)?,
+            }
+        }
+
+        if checks.metadata {
+            let license = match &dep.metadata {
+                Some(CrateMetadata {
+                    license: Some(license),
+                    ..
+                }) => license.as_str(),
+                Some(CrateMetadata {
+                    license_file: true, ..
+                }) => "license-file",
+                _ => "",
+            };
+            cell(
+                out,
+                license,
+                &format!("{} {}", emoji(dep.passed_metadata), license),
+            )?;
+        }
+
+        let reasons = dep.reason_messages().join("; ");
+        cell(out, &reasons, &reasons)?;
+
+        writeln!(out, "</tr>")?;
+        details(out, dep, titles.len())?;
+        writeln!(out, "</tbody>")?;
+    }
+
+    writeln!(out, "</table>")?;
+
+    Ok(())
+}
+
+fn summary_table(out: &mut dyn Write, title: &str, rows: &[(String, usize)]) -> Result<()> {
+    writeln!(out, "<table class=\"summary\">")?;
+    writeln!(out, "<tr><th>{}</th><th>Count</th></tr>", escape(title))?;
+    for (name, count) in rows {
+        writeln!(out, "<tr><td>{}</td><td>{}</td></tr>", escape(name), count)?;
+    }
+    writeln!(out, "</table>")?;
+    Ok(())
+}
+
+fn show_summary(out: &mut dyn Write, summary: &Summary) -> Result<()> {
+    writeln!(out, "<h2>Summary</h2>")?;
+
+    let totals = [
+        ("Total", summary.total),
+        ("Passed", summary.outcomes.pass),
+        ("Overridden", summary.outcomes.overridden),
+        ("Warnings", summary.outcomes.warn),
+        ("Failed", summary.outcomes.fail),
+        ("Ignored", summary.ignored),
+        ("Excluded", summary.excluded),
+        ("No definition", summary.missing_definition),
+        ("Locally curated", summary.curated),
+        ("Missing license", summary.missing_license),
+        ("Unparsable license", summary.unparsable_license),
+    ]
+    .map(|(title, count)| (title.to_string(), count));
+    summary_table(out, "Dependencies", &totals)?;
+
+    let licenses: Vec<_> = summary
+        .licenses_by_count()
+        .into_iter()
+        .map(|(license, count)| (license.to_string(), count))
+        .collect();
+    summary_table(out, "License", &licenses)?;
+
+    for histogram in &summary.scores {
+        let buckets: Vec<_> = histogram
+            .buckets
+            .iter()
+            .enumerate()
+            .map(|(bucket, count)| (summary::bucket_title(bucket), *count))
+            .collect();
+        summary_table(out, &format!("Score ({})", histogram.score_type), &buckets)?;
+    }
+
+    Ok(())
+}
+
+/// Show the dependencies as a self-contained HTML document.
+pub fn show(
+    out: &mut dyn Write,
+    options: &Options,
+    checks: &Checks,
+    sections: &[Section],
+    summary: &Summary,
+) -> Result<()> {
+    header(out, "ClearlyDefined report")?;
+
+    writeln!(out, "<div class=\"filter\">Show:")?;
+    for (value, label) in [
+        ("all", "All"),
+        ("pass", "Passed"),
+        ("warn", "Warnings"),
+        ("fail", "Failed"),
+    ] {
+        writeln!(
+            out,
+            "<label><input type=\"radio\" name=\"filter\" value=\"{}\"{}> {}</label>",
+            value,
+            if value == "all" { " checked" } else { "" },
+            label
+        )?;
+    }
+    writeln!(out, "</div>")?;
+
+    for s in sections {
+        if let Some(title) = s.title {
+            writeln!(out, "<h2>{}</h2>", escape(title))?;
+        }
+        section(out, options, checks, s.dependencies)?;
+    }
+
+    show_summary(out, summary)?;
+
+    footer(out)
+}
+
+/// Show a plain table as a self-contained HTML document.
+pub fn show_table(out: &mut dyn Write, table: &Table) -> Result<()> {
+    header(out, "ClearlyDefined report")?;
+    table.print_html(out)?;
+    footer(out)
+}
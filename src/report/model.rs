@@ -0,0 +1,321 @@
+/*
+ * Copyright (c) 2020 Red Hat Inc.
+ *
+ * See the NOTICE file(s) distributed with this work for additional
+ * information regarding copyright ownership.
+ *
+ * This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License 2.0 which is available at
+ * http://www.eclipse.org/legal/epl-2.0
+ *
+ * SPDX-License-Identifier: EPL-2.0
+ */
+
+//! A serializable model of the report, for formats which don't render a table.
+
+use super::{clearly_link, declared_license, Checks, Options, Section, Summary};
+use crate::data::{Dependency, Outcome, UpstreamCuration};
+use crate::diff::{Change, DiffEntry};
+use crate::policy::ScoreType;
+use serde::Serialize;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    pub metadata: Metadata,
+    pub policy: Policy,
+    /// The dependencies of the first section.
+    pub dependencies: Vec<DependencyModel>,
+    pub sections: Vec<SectionModel>,
+    pub summary: Summary,
+}
+
+/// Information about the run.
+#[derive(Debug, Clone, Serialize)]
+pub struct Metadata {
+    pub tool: String,
+    pub version: String,
+    pub input: String,
+    pub timestamp: String,
+}
+
+/// The checks which were performed.
+#[derive(Debug, Clone, Serialize)]
+pub struct Policy {
+    pub scores: Vec<ScoreRequirementModel>,
+    pub score_overrides: Vec<String>,
+    pub license_check: bool,
+    pub approve_all: bool,
+    pub approve_osi: bool,
+    pub approved_licenses: Vec<String>,
+    pub allowed_categories: Vec<String>,
+    pub denied_categories: Vec<String>,
+    pub license_change_check: bool,
+    pub metadata_check: bool,
+    pub deny_warnings: bool,
+    pub excluded: Vec<String>,
+    pub ignored: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScoreRequirementModel {
+    pub score_type: ScoreType,
+    pub score: u64,
+    pub warn: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SectionModel {
+    pub title: Option<String>,
+    pub dependencies: Vec<DependencyModel>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DependencyModel {
+    pub name: String,
+    pub version: String,
+    pub link: String,
+    pub passed: bool,
+    pub declared_license: Option<String>,
+    pub declared_license_error: Option<String>,
+    pub effective_license: Option<String>,
+    pub license: Outcome,
+    pub scores: Vec<ScoreModel>,
+    pub score_details: Vec<ScoreDetailModel>,
+    /// The fields which were overridden by a local curation.
+    pub curated: Vec<String>,
+    /// The curations submitted upstream, if looked up.
+    pub curations: Vec<UpstreamCuration>,
+    pub previous: Option<PreviousModel>,
+    pub license_change: Outcome,
+    pub crate_license: Option<String>,
+    pub crate_license_file: bool,
+    pub metadata: Outcome,
+    pub reasons: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScoreModel {
+    pub score_type: ScoreType,
+    pub score: Option<u64>,
+    pub required: u64,
+    pub outcome: Outcome,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScoreDetailModel {
+    pub name: String,
+    pub score: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PreviousModel {
+    pub version: String,
+    pub declared_license: Option<String>,
+}
+
+fn strings<T: ToString>(items: &[T]) -> Vec<String> {
+    items.iter().map(|i| i.to_string()).collect()
+}
+
+impl Report {
+    pub fn new(
+        options: &Options,
+        checks: &Checks,
+        sections: &[Section],
+        summary: &Summary,
+    ) -> Self {
+        let sections: Vec<_> = sections
+            .iter()
+            .map(|s| SectionModel {
+                title: s.title.map(Into::into),
+                dependencies: s
+                    .dependencies
+                    .iter()
+                    .map(|dep| DependencyModel::new(dep, options, checks))
+                    .collect(),
+            })
+            .collect();
+
+        Report {
+            metadata: Metadata {
+                tool: env!("CARGO_PKG_NAME").into(),
+                version: env!("CARGO_PKG_VERSION").into(),
+                input: options.input.display().to_string(),
+                timestamp: OffsetDateTime::now_utc()
+                    .format(&Rfc3339)
+                    .unwrap_or_default(),
+            },
+            policy: Policy {
+                scores: checks
+                    .score_requirements
+                    .iter()
+                    .map(|req| ScoreRequirementModel {
+                        score_type: req.score_type,
+                        score: req.score,
+                        warn: req.warn,
+                    })
+                    .collect(),
+                score_overrides: options
+                    .score_overrides
+                    .iter()
+                    .map(|o| format!("{}={}", o.selector, o.score))
+                    .collect(),
+                license_check: checks.license,
+                approve_all: options.approve_all,
+                approve_osi: options.approve_osi,
+                approved_licenses: options
+                    .approved_licenses
+                    .iter()
+                    .map(|l| l.name.to_string())
+                    .collect(),
+                allowed_categories: options
+                    .allowed_categories
+                    .iter()
+                    .map(|c| c.to_string())
+                    .collect(),
+                denied_categories: options
+                    .denied_categories
+                    .iter()
+                    .map(|c| c.to_string())
+                    .collect(),
+                license_change_check: checks.license_change,
+                metadata_check: checks.metadata,
+                deny_warnings: options.deny_warnings,
+                excluded: strings(&options.exclude),
+                ignored: strings(&options.ignore),
+            },
+            dependencies: sections
+                .first()
+                .map(|s| s.dependencies.clone())
+                .unwrap_or_default(),
+            sections,
+            summary: summary.clone(),
+        }
+    }
+}
+
+impl DependencyModel {
+    pub fn new(dep: &Dependency, options: &Options, checks: &Checks) -> Self {
+        let (declared_license, declared_license_error) = match declared_license(dep, options.lax) {
+            Ok(license) => (license, None),
+            Err(err) => (
+                dep.declared_license().map(Into::into),
+                Some(err.to_string()),
+            ),
+        };
+
+        let score =
+            |score_type: ScoreType| dep.clearly_defined.as_ref().map(|cd| cd.score(score_type));
+
+        DependencyModel {
+            name: dep.name.clone(),
+            version: dep.version.to_string(),
+            link: clearly_link(dep),
+            passed: dep.passed(),
+            declared_license,
+            declared_license_error,
+            effective_license: dep.effective_license.clone(),
+            license: dep.passed_license,
+            scores: checks
+                .score_requirements
+                .iter()
+                .enumerate()
+                .map(|(i, req)| ScoreModel {
+                    score_type: req.score_type,
+                    score: score(req.score_type),
+                    required: req.score,
+                    outcome: dep.passed_score.get(i).copied().unwrap_or(Outcome::Ignore),
+                })
+                .collect(),
+            score_details: dep
+                .clearly_defined
+                .iter()
+                .flat_map(|cd| &cd.score_details)
+                .map(|(name, score)| ScoreDetailModel {
+                    name: name.clone(),
+                    score: *score,
+                })
+                .collect(),
+            curated: dep
+                .clearly_defined
+                .iter()
+                .flat_map(|cd| cd.curated.clone())
+                .collect(),
+            curations: dep.curations.clone(),
+            previous: dep.previous.as_ref().map(|prev| PreviousModel {
+                version: prev.version.to_string(),
+                declared_license: prev.declared_license.clone(),
+            }),
+            license_change: dep.passed_license_change,
+            crate_license: dep.metadata.as_ref().and_then(|m| m.license.clone()),
+            crate_license_file: dep
+                .metadata
+                .as_ref()
+                .map(|m| m.license_file)
+                .unwrap_or_default(),
+            metadata: dep.passed_metadata,
+            reasons: dep.reason_messages().into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+/// A serializable model of the changes between two lockfiles.
+#[derive(Debug, Clone, Serialize)]
+pub struct DiffReport {
+    pub changes: Vec<DiffEntryModel>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiffEntryModel {
+    pub change: Change,
+    pub name: String,
+    pub old: Option<DiffVersionModel>,
+    pub new: Option<DiffVersionModel>,
+    pub license_changed: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiffVersionModel {
+    pub version: String,
+    pub declared_license: Option<String>,
+    pub scores: Vec<DiffScoreModel>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiffScoreModel {
+    pub score_type: ScoreType,
+    pub score: Option<u64>,
+}
+
+impl DiffReport {
+    pub fn new(score_types: &[ScoreType], entries: &[DiffEntry]) -> Self {
+        let version = |dep: &Option<Dependency>| {
+            dep.as_ref().map(|dep| DiffVersionModel {
+                version: dep.version.to_string(),
+                declared_license: dep.declared_license().map(Into::into),
+                scores: score_types
+                    .iter()
+                    .map(|&score_type| DiffScoreModel {
+                        score_type,
+                        score: dep.clearly_defined.as_ref().map(|cd| cd.score(score_type)),
+                    })
+                    .collect(),
+            })
+        };
+
+        DiffReport {
+            changes: entries
+                .iter()
+                .map(|entry| DiffEntryModel {
+                    change: entry.change(),
+                    name: entry.name.clone(),
+                    old: version(&entry.old),
+                    new: version(&entry.new),
+                    license_changed: entry.license_changed(),
+                })
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,200 @@
+/*
+ * Copyright (c) 2020 Red Hat Inc.
+ *
+ * See the NOTICE file(s) distributed with this work for additional
+ * information regarding copyright ownership.
+ *
+ * This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License 2.0 which is available at
+ * http://www.eclipse.org/legal/epl-2.0
+ *
+ * SPDX-License-Identifier: EPL-2.0
+ */
+
+//! A SARIF 2.1.0 log, reporting failures and warnings as results.
+
+use super::{clearly_link, Checks, Options, Section, Summary};
+use crate::data::{Check, Dependency, Outcome};
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::io::Write;
+
+const SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+const INFORMATION_URI: &str = "https://github.com/ctron/cargo-clearlydefined";
+
+const RULES: &[(&str, &str)] = &[
+    (
+        "license",
+        "The declared license must pass the license checks",
+    ),
+    ("license-change", "The declared license must not change"),
+    (
+        "score",
+        "The ClearlyDefined score must meet the required score",
+    ),
+    (
+        "metadata",
+        "The declared license must agree with the crate metadata",
+    ),
+];
+
+fn result(options: &Options, dep: &Dependency, rule: Check, outcome: Outcome) -> Option<Value> {
+    let level = match outcome {
+        Outcome::Fail => "error",
+        Outcome::Warn => "warning",
+        Outcome::Pass | Outcome::Override | Outcome::Ignore => return None,
+    };
+
+    // only the reasons of this check, the other ones are reported by their own results
+    let reasons = dep.reasons_of(rule);
+    let mut message = format!("{} {} failed the {} check", dep.name, dep.version, rule);
+    if !reasons.is_empty() {
+        message = format!("{}: {}", message, reasons.join("; "));
+    }
+
+    Some(json!({
+        "ruleId": rule.to_string(),
+        "level": level,
+        "message": { "text": message },
+        "locations": [{
+            "physicalLocation": {
+                "artifactLocation": { "uri": options.input.display().to_string() }
+            }
+        }],
+        "properties": {
+            "name": dep.name,
+            "version": dep.version.to_string(),
+            "link": clearly_link(dep),
+            "effective_license": dep.effective_license,
+            "curated": dep.clearly_defined.as_ref().map(|cd| &cd.curated),
+        }
+    }))
+}
+
+/// Show the failures and warnings of all sections as a SARIF log.
+pub fn show(
+    out: &mut dyn Write,
+    options: &Options,
+    checks: &Checks,
+    sections: &[Section],
+    summary: &Summary,
+) -> Result<()> {
+    let mut results = Vec::new();
+
+    for dep in sections.iter().flat_map(|s| s.dependencies) {
+        if checks.license {
+            results.extend(result(options, dep, Check::License, dep.passed_license));
+        }
+        if checks.license_change {
+            results.extend(result(
+                options,
+                dep,
+                Check::LicenseChange,
+                dep.passed_license_change,
+            ));
+        }
+        // report the worst outcome of all score requirements only once
+        let score = dep
+            .passed_score
+            .iter()
+            .copied()
+            .find(|o| o.is_fail())
+            .or_else(|| {
+                dep.passed_score
+                    .iter()
+                    .copied()
+                    .find(|o| matches!(o, Outcome::Warn))
+            });
+        if let Some(score) = score {
+            results.extend(result(options, dep, Check::Score, score));
+        }
+        if checks.metadata {
+            results.extend(result(options, dep, Check::Metadata, dep.passed_metadata));
+        }
+    }
+
+    let rules: Vec<_> = RULES
+        .iter()
+        .map(|(id, description)| {
+            json!({
+                "id": id,
+                "shortDescription": { "text": description },
+            })
+        })
+        .collect();
+
+    let log = json!({
+        "$schema": SCHEMA,
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": env!("CARGO_PKG_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": INFORMATION_URI,
+                    "rules": rules,
+                }
+            },
+            "results": results,
+            "properties": { "summary": summary },
+        }]
+    });
+
+    serde_json::to_writer_pretty(&mut *out, &log)?;
+    writeln!(out)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::policy::{ScoreRequirement, ScoreType};
+
+    #[test]
+    fn reasons_per_check() {
+        let mut dep = Dependency::new("a".into(), "1.0.0".parse().unwrap());
+        dep.passed_license = Outcome::Fail;
+        dep.add_reason(Check::License, "Missing license information");
+        dep.passed_score = vec![Outcome::Fail];
+        dep.add_reason(Check::Score, "The effective score of 0 is below 80");
+        let deps = [dep];
+
+        let reqs = [ScoreRequirement {
+            score_type: ScoreType::Effective,
+            score: 80,
+            warn: None,
+        }];
+        let checks = Checks {
+            score_requirements: &reqs,
+            license: true,
+            license_checks: &[],
+            license_change: false,
+            metadata: false,
+        };
+        let sections = [Section {
+            title: None,
+            dependencies: &deps,
+        }];
+        let options = Options::default();
+        let summary = Summary::new(&options, &checks, &deps, 0);
+
+        let mut out = Vec::new();
+        show(&mut out, &options, &checks, &sections, &summary).unwrap();
+        let log: Value = serde_json::from_slice(&out).unwrap();
+
+        let messages: Vec<_> = log["runs"][0]["results"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| r["message"]["text"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                "a 1.0.0 failed the license check: Missing license information",
+                "a 1.0.0 failed the score check: The effective score of 0 is below 80",
+            ]
+        );
+    }
+}
@@ -0,0 +1,249 @@
+/*
+ * Copyright (c) 2020 Red Hat Inc.
+ *
+ * See the NOTICE file(s) distributed with this work for additional
+ * information regarding copyright ownership.
+ *
+ * This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License 2.0 which is available at
+ * http://www.eclipse.org/legal/epl-2.0
+ *
+ * SPDX-License-Identifier: EPL-2.0
+ */
+
+//! Summary statistics over all checked dependencies.
+
+use super::{declared_license, Checks, Options};
+use crate::data::{Dependency, Outcome};
+use crate::policy::ScoreType;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// The number of buckets of the score histogram, each covering ten points.
+const BUCKETS: usize = 10;
+
+/// The number of dependencies, by their overall outcome.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Outcomes {
+    pub pass: usize,
+    #[serde(rename = "override")]
+    pub overridden: usize,
+    pub warn: usize,
+    pub fail: usize,
+    pub ignore: usize,
+}
+
+/// The distribution of one score type, in buckets of ten points.
+#[derive(Debug, Clone, Serialize)]
+pub struct Histogram {
+    pub score_type: ScoreType,
+    /// The number of dependencies per bucket, starting with `0-9`. The last bucket is `90-100`.
+    pub buckets: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Summary {
+    pub total: usize,
+    pub outcomes: Outcomes,
+    /// The number of dependencies per (normalized) declared license.
+    pub licenses: BTreeMap<String, usize>,
+    pub scores: Vec<Histogram>,
+    pub missing_definition: usize,
+    pub curated: usize,
+    pub missing_license: usize,
+    pub unparsable_license: usize,
+    pub excluded: usize,
+    pub ignored: usize,
+}
+
+/// The overall outcome of a dependency, the worst outcome of all of its checks.
+pub fn overall(dep: &Dependency) -> Outcome {
+    let outcomes: Vec<_> = [
+        dep.passed_license,
+        dep.passed_license_change,
+        dep.passed_metadata,
+    ]
+    .into_iter()
+    .chain(dep.passed_score.iter().copied())
+    .collect();
+
+    let any = |f: fn(&Outcome) -> bool| outcomes.iter().any(f);
+
+    if !dep.passed() {
+        Outcome::Fail
+    } else if any(|o| matches!(o, Outcome::Warn)) {
+        Outcome::Warn
+    } else if any(|o| matches!(o, Outcome::Override)) {
+        Outcome::Override
+    } else if any(|o| matches!(o, Outcome::Pass)) {
+        Outcome::Pass
+    } else {
+        Outcome::Ignore
+    }
+}
+
+impl Summary {
+    pub fn new<'a>(
+        options: &Options,
+        checks: &Checks,
+        dependencies: impl IntoIterator<Item = &'a Dependency>,
+        excluded: usize,
+    ) -> Self {
+        let mut score_types = Vec::<ScoreType>::new();
+        for req in checks.score_requirements {
+            if !score_types.contains(&req.score_type) {
+                score_types.push(req.score_type);
+            }
+        }
+
+        let mut summary = Summary {
+            total: 0,
+            outcomes: Outcomes::default(),
+            licenses: BTreeMap::new(),
+            scores: score_types
+                .into_iter()
+                .map(|score_type| Histogram {
+                    score_type,
+                    buckets: vec![0; BUCKETS],
+                })
+                .collect(),
+            missing_definition: 0,
+            curated: 0,
+            missing_license: 0,
+            unparsable_license: 0,
+            excluded,
+            ignored: 0,
+        };
+
+        for dep in dependencies {
+            summary.total += 1;
+
+            if options
+                .ignore
+                .iter()
+                .any(|s| s.matches(&dep.name, &dep.version))
+            {
+                summary.ignored += 1;
+                summary.outcomes.ignore += 1;
+            } else {
+                match overall(dep) {
+                    Outcome::Pass => summary.outcomes.pass += 1,
+                    Outcome::Override => summary.outcomes.overridden += 1,
+                    Outcome::Warn => summary.outcomes.warn += 1,
+                    Outcome::Fail => summary.outcomes.fail += 1,
+                    Outcome::Ignore => summary.outcomes.ignore += 1,
+                }
+            }
+
+            match declared_license(dep, options.lax) {
+                Ok(Some(license)) => *summary.licenses.entry(license).or_default() += 1,
+                Ok(None) => summary.missing_license += 1,
+                Err(_) => summary.unparsable_license += 1,
+            }
+
+            match &dep.clearly_defined {
+                Some(cd) => {
+                    if !cd.curated.is_empty() {
+                        summary.curated += 1;
+                    }
+                    for histogram in &mut summary.scores {
+                        let score = cd.score(histogram.score_type) as usize;
+                        histogram.buckets[(score / 10).min(BUCKETS - 1)] += 1;
+                    }
+                }
+                None => summary.missing_definition += 1,
+            }
+        }
+
+        summary
+    }
+
+    /// The licenses, ordered by the number of dependencies using them.
+    pub fn licenses_by_count(&self) -> Vec<(&str, usize)> {
+        let mut licenses: Vec<_> = self
+            .licenses
+            .iter()
+            .map(|(license, count)| (license.as_str(), *count))
+            .collect();
+        licenses.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        licenses
+    }
+}
+
+/// The title of a histogram bucket.
+pub fn bucket_title(bucket: usize) -> String {
+    match bucket {
+        b if b + 1 == BUCKETS => format!("{}-100", b * 10),
+        b => format!("{}-{}", b * 10, b * 10 + 9),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{ClearlyDefined, License};
+    use crate::policy::ScoreRequirement;
+
+    fn dependency(name: &str, license: Option<&str>, score: u64, outcome: Outcome) -> Dependency {
+        let mut dep = Dependency::new(name.into(), "1.0.0".parse().unwrap());
+        dep.clearly_defined = Some(ClearlyDefined::new(
+            license.map(|raw| License { raw: raw.into() }),
+            score,
+            score,
+            vec![],
+        ));
+        dep.passed_license = outcome;
+        dep.passed_score = vec![Outcome::Pass];
+        dep
+    }
+
+    #[test]
+    fn summarize() {
+        let options = Options {
+            ignore: vec!["d".parse().unwrap()],
+            ..Default::default()
+        };
+        let reqs = [ScoreRequirement {
+            score_type: ScoreType::Effective,
+            score: 80,
+            warn: None,
+        }];
+        let checks = Checks {
+            score_requirements: &reqs,
+            license: true,
+            license_checks: &[],
+            license_change: false,
+            metadata: false,
+        };
+        let deps = [
+            dependency("a", Some("MIT"), 100, Outcome::Pass),
+            dependency("b", Some("MIT"), 95, Outcome::Warn),
+            dependency("c", Some("not a license"), 9, Outcome::Fail),
+            dependency("d", None, 10, Outcome::Pass),
+        ];
+
+        let summary = Summary::new(&options, &checks, &deps, 2);
+
+        assert_eq!(summary.total, 4);
+        assert_eq!(
+            (
+                summary.outcomes.pass,
+                summary.outcomes.warn,
+                summary.outcomes.fail,
+                summary.outcomes.ignore
+            ),
+            (1, 1, 1, 1)
+        );
+        assert_eq!(summary.licenses_by_count(), vec![("MIT", 2)]);
+        assert_eq!(
+            summary.scores[0].buckets,
+            vec![1, 1, 0, 0, 0, 0, 0, 0, 0, 2]
+        );
+        assert_eq!(
+            (summary.missing_license, summary.unparsable_license),
+            (1, 1)
+        );
+        assert_eq!((summary.excluded, summary.ignored), (2, 1));
+        assert_eq!(bucket_title(9), "90-100");
+    }
+}
@@ -0,0 +1,51 @@
+/*
+ * Copyright (c) 2020 Red Hat Inc.
+ *
+ * See the NOTICE file(s) distributed with this work for additional
+ * information regarding copyright ownership.
+ *
+ * This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License 2.0 which is available at
+ * http://www.eclipse.org/legal/epl-2.0
+ *
+ * SPDX-License-Identifier: EPL-2.0
+ */
+
+//! A report rendered from a user provided template.
+
+use super::model::Report;
+use super::{Checks, Options, Section, Summary};
+use anyhow::{anyhow, Context, Result};
+use minijinja::Environment;
+use std::fs;
+use std::io::Write;
+
+/// Render the report using the template provided by `--template`.
+pub fn show(
+    out: &mut dyn Write,
+    options: &Options,
+    checks: &Checks,
+    sections: &[Section],
+    summary: &Summary,
+) -> Result<()> {
+    let path = options
+        .template
+        .as_ref()
+        .ok_or_else(|| anyhow!("The template output format requires a template (--template)"))?;
+
+    let source = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read template: {}", path.display()))?;
+
+    let mut env = Environment::new();
+    env.add_template("report", &source)
+        .with_context(|| format!("Failed to parse template: {}", path.display()))?;
+
+    let report = Report::new(options, checks, sections, summary);
+    let output = env
+        .get_template("report")?
+        .render(&report)
+        .with_context(|| format!("Failed to render template: {}", path.display()))?;
+    out.write_all(output.as_bytes())?;
+
+    Ok(())
+}
@@ -0,0 +1,342 @@
+/*
+ * Copyright (c) 2020 Red Hat Inc.
+ *
+ * See the NOTICE file(s) distributed with this work for additional
+ * information regarding copyright ownership.
+ *
+ * This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License 2.0 which is available at
+ * http://www.eclipse.org/legal/epl-2.0
+ *
+ * SPDX-License-Identifier: EPL-2.0
+ */
+
+//! Sources of ClearlyDefined definitions.
+
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use reqwest::header::{HeaderValue, AUTHORIZATION};
+use reqwest::{Client, StatusCode};
+use semver::Version;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+/// The URL of the public ClearlyDefined API.
+pub const API_URL: &str = "https://api.clearlydefined.io";
+
+/// The ClearlyDefined coordinates of a crate from crates.io.
+pub fn coordinates(name: &str, version: &Version) -> String {
+    format!("crate/cratesio/-/{}/{}", name, version)
+}
+
+/// A source of ClearlyDefined definitions, of crates from crates.io.
+#[async_trait]
+pub trait DefinitionSource: Send + Sync {
+    /// Get the raw definition of a crate, or `None` if the source doesn't know the crate.
+    async fn definition(&self, name: &str, version: &Version) -> Result<Option<Value>>;
+}
+
+/// The ClearlyDefined HTTP API.
+pub struct HttpSource {
+    client: Client,
+    url: String,
+    authorization: Option<HeaderValue>,
+}
+
+impl HttpSource {
+    pub fn new(client: Client) -> Self {
+        Self::with_url(client, API_URL)
+    }
+
+    /// Use a different instance of the API, e.g. a mirror.
+    pub fn with_url(client: Client, url: impl Into<String>) -> Self {
+        HttpSource {
+            client,
+            url: url.into().trim_end_matches('/').to_string(),
+            authorization: None,
+        }
+    }
+
+    /// Authenticate using a bearer token.
+    ///
+    /// The header is marked as sensitive, so that it doesn't show up in logs.
+    pub fn with_token(mut self, token: &str) -> Result<Self> {
+        let mut value = HeaderValue::from_str(&format!("Bearer {}", token))
+            .map_err(|_| anyhow!("Invalid API token"))?;
+        value.set_sensitive(true);
+        self.authorization = Some(value);
+        Ok(self)
+    }
+}
+
+impl HttpSource {
+    fn get(&self, url: &str) -> reqwest::RequestBuilder {
+        let request = self.client.get(url);
+        match &self.authorization {
+            Some(authorization) => request.header(AUTHORIZATION, authorization.clone()),
+            None => request,
+        }
+    }
+
+    /// Get the curations of a crate, including the pull requests of the contributions.
+    pub async fn curations(&self, name: &str, version: &Version) -> Result<Value> {
+        let url = format!(
+            "{}/curations/{}?expand=prs",
+            self.url,
+            coordinates(name, version)
+        );
+
+        Ok(self
+            .get(&url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?)
+    }
+
+    /// Submit a curation, returning the response, which contains the URL of the pull request.
+    pub async fn submit_curation(&self, contribution: &Value) -> Result<Value> {
+        let url = format!("{}/curations", self.url);
+
+        let mut request = self.client.patch(&url).json(contribution);
+        if let Some(authorization) = &self.authorization {
+            request = request.header(AUTHORIZATION, authorization.clone());
+        }
+
+        let response = request.send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            let text = response.text().await.unwrap_or_default();
+            return Err(anyhow!("Failed to submit curation ({}): {}", status, text));
+        }
+
+        Ok(response.json().await?)
+    }
+}
+
+#[async_trait]
+impl DefinitionSource for HttpSource {
+    async fn definition(&self, name: &str, version: &Version) -> Result<Option<Value>> {
+        let url = format!("{}/definitions/{}", self.url, coordinates(name, version));
+
+        let response = self.get(&url).send().await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        Ok(Some(response.error_for_status()?.json().await?))
+    }
+}
+
+/// The path of a definition in a directory: `<dir>/<name>/<version>.json`.
+fn definition_path(dir: &Path, name: &str, version: &Version) -> PathBuf {
+    dir.join(name).join(format!("{}.json", version))
+}
+
+fn read_definition(path: &Path) -> Result<Option<Value>> {
+    if !path.is_file() {
+        return Ok(None);
+    }
+
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read definition: {}", path.display()))?;
+    let definition = serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse definition: {}", path.display()))?;
+
+    Ok(Some(definition))
+}
+
+/// A directory of static JSON definitions, stored as `<dir>/<name>/<version>.json`.
+pub struct FixtureSource {
+    dir: PathBuf,
+}
+
+impl FixtureSource {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        FixtureSource { dir: dir.into() }
+    }
+}
+
+#[async_trait]
+impl DefinitionSource for FixtureSource {
+    async fn definition(&self, name: &str, version: &Version) -> Result<Option<Value>> {
+        read_definition(&definition_path(&self.dir, name, version))
+    }
+}
+
+/// A local cache in front of another source.
+///
+/// The cache uses the same layout as the [`FixtureSource`], so a cache directory can be used as
+/// fixtures as well.
+pub struct CacheSource {
+    dir: PathBuf,
+    inner: Box<dyn DefinitionSource>,
+}
+
+impl CacheSource {
+    pub fn new(dir: impl Into<PathBuf>, inner: Box<dyn DefinitionSource>) -> Self {
+        CacheSource {
+            dir: dir.into(),
+            inner,
+        }
+    }
+}
+
+#[async_trait]
+impl DefinitionSource for CacheSource {
+    async fn definition(&self, name: &str, version: &Version) -> Result<Option<Value>> {
+        let path = definition_path(&self.dir, name, version);
+        if let Some(definition) = read_definition(&path)? {
+            log::debug!("Cache hit: {}/{}", name, version);
+            return Ok(Some(definition));
+        }
+
+        let definition = self.inner.definition(name, version).await?;
+        if let Some(definition) = &definition {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).with_context(|| {
+                    format!("Failed to create cache directory: {}", parent.display())
+                })?;
+            }
+            fs::write(&path, serde_json::to_vec_pretty(definition)?)
+                .with_context(|| format!("Failed to write cache: {}", path.display()))?;
+        }
+
+        Ok(definition)
+    }
+}
+
+/// A bundle of definitions, keyed by their coordinates, stored in a single JSON file.
+#[derive(Debug, Default)]
+pub struct BundleSource {
+    definitions: BTreeMap<String, Value>,
+}
+
+impl BundleSource {
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = File::open(path)
+            .with_context(|| format!("Failed to open definitions: {}", path.display()))?;
+        let definitions = serde_json::from_reader(BufReader::new(file))
+            .with_context(|| format!("Failed to parse definitions: {}", path.display()))?;
+        Ok(BundleSource { definitions })
+    }
+
+    pub fn store(&self, path: &Path) -> Result<()> {
+        let file = File::create(path)
+            .with_context(|| format!("Failed to create definitions: {}", path.display()))?;
+        serde_json::to_writer_pretty(BufWriter::new(file), &self.definitions)?;
+        Ok(())
+    }
+
+    pub fn insert(&mut self, name: &str, version: &Version, definition: Value) {
+        self.definitions
+            .insert(coordinates(name, version), definition);
+    }
+
+    pub fn len(&self) -> usize {
+        self.definitions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.definitions.is_empty()
+    }
+}
+
+#[async_trait]
+impl DefinitionSource for BundleSource {
+    async fn definition(&self, name: &str, version: &Version) -> Result<Option<Value>> {
+        let definition = self.definitions.get(&coordinates(name, version)).cloned();
+        if definition.is_none() {
+            log::warn!("No definition of {}/{} in the bundle", name, version);
+        }
+        Ok(definition)
+    }
+}
+
+/// Multiple sources, asked in order until one knows the crate.
+///
+/// If a source fails, the next one is asked. Only if all sources fail, the error is returned.
+pub struct ChainSource {
+    sources: Vec<Box<dyn DefinitionSource>>,
+}
+
+impl ChainSource {
+    pub fn new(sources: Vec<Box<dyn DefinitionSource>>) -> Self {
+        ChainSource { sources }
+    }
+}
+
+#[async_trait]
+impl DefinitionSource for ChainSource {
+    async fn definition(&self, name: &str, version: &Version) -> Result<Option<Value>> {
+        let mut error = None;
+
+        for source in &self.sources {
+            match source.definition(name, version).await {
+                Ok(Some(definition)) => return Ok(Some(definition)),
+                Ok(None) => {}
+                Err(err) => {
+                    log::warn!(
+                        "Failed to fetch definition of {}/{}: {}",
+                        name,
+                        version,
+                        err
+                    );
+                    error = Some(err);
+                }
+            }
+        }
+
+        match error {
+            Some(err) => Err(anyhow!(err).context(format!(
+                "All sources failed to provide the definition of {}/{}",
+                name, version
+            ))),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn chain_and_cache() {
+        let dir =
+            std::env::temp_dir().join(format!("clearlydefined-source-{}", std::process::id()));
+        let fixtures = dir.join("fixtures");
+        let cache = dir.join("cache");
+        let version = Version::new(1, 0, 0);
+
+        fs::create_dir_all(fixtures.join("a")).unwrap();
+        fs::write(
+            fixtures.join("a").join("1.0.0.json"),
+            json!({"licensed": {"declared": "MIT"}}).to_string(),
+        )
+        .unwrap();
+
+        let source = CacheSource::new(
+            &cache,
+            Box::new(ChainSource::new(vec![
+                Box::new(FixtureSource::new(dir.join("missing"))),
+                Box::new(FixtureSource::new(&fixtures)),
+            ])),
+        );
+
+        let definition = source.definition("a", &version).await.unwrap().unwrap();
+        assert_eq!(definition["licensed"]["declared"], "MIT");
+        assert!(source.definition("b", &version).await.unwrap().is_none());
+
+        // served from the cache, once the fixture is gone
+        fs::remove_dir_all(&fixtures).unwrap();
+        assert!(source.definition("a", &version).await.unwrap().is_some());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
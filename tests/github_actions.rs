@@ -0,0 +1,54 @@
+/*
+ * Copyright (c) 2020 Red Hat Inc.
+ *
+ * See the NOTICE file(s) distributed with this work for additional
+ * information regarding copyright ownership.
+ *
+ * This program and the accompanying materials are made available under the
+ * terms of the Eclipse Public License 2.0 which is available at
+ * http://www.eclipse.org/legal/epl-2.0
+ *
+ * SPDX-License-Identifier: EPL-2.0
+ */
+
+use std::fs;
+use std::process::Command;
+
+#[test]
+fn json_report_without_annotations() {
+    let dir = std::env::temp_dir().join(format!("cargo-clearlydefined-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    let lockfile = dir.join("Cargo.lock");
+    fs::write(
+        &lockfile,
+        "version = 3\n\n[[package]]\nname = \"a\"\nversion = \"1.0.0\"\n",
+    )
+    .unwrap();
+    let definitions = dir.join("definitions.json");
+    fs::write(
+        &definitions,
+        r#"{"crate/cratesio/-/a/1.0.0": {"licensed": {"declared": "GPL-3.0-only", "score": {"total": 10}}, "scores": {"effective": 10}}}"#,
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_cargo-clearlydefined"))
+        .arg("clearlydefined")
+        .arg("--input")
+        .arg(&lockfile)
+        .arg("--definitions")
+        .arg(&definitions)
+        .args(["--approve", "MIT", "-o", "json"])
+        .env("GITHUB_ACTIONS", "true")
+        .env_remove("GITHUB_STEP_SUMMARY")
+        .output()
+        .unwrap();
+
+    fs::remove_dir_all(&dir).unwrap();
+
+    // the dependency fails, which would be annotated
+    assert!(!output.status.success());
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["dependencies"][0]["name"], "a");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--output"));
+}